@@ -0,0 +1,257 @@
+mod service;
+
+use std::time::Duration;
+
+pub use self::service::open_system_file;
+pub(crate) use self::service::{open_file, Allocated};
+
+/// Additional options for opening a system file.
+///
+/// By default, the file is opened for reading and writing, and created if it doesn't exist.
+#[derive(Debug, Clone, Default)]
+pub struct SystemFileOptions {
+    pub(crate) read_only: bool,
+    pub(crate) must_exist: bool,
+    pub(crate) create_new: bool,
+    pub(crate) truncate: bool,
+    pub(crate) strict: bool,
+    pub(crate) lock: bool,
+    pub(crate) lock_timeout: Option<Duration>,
+}
+
+impl SystemFileOptions {
+    /// Set if the file is opened for reading only.
+    ///
+    /// Read-only files must already exist, and respond to writes with `Error::NotSupported`.
+    pub fn read_only(mut self, value: bool) -> Self {
+        self.read_only = value;
+        self
+    }
+
+    /// Set if opening fails when the file doesn't exist, instead of creating it.
+    pub fn must_exist(mut self, value: bool) -> Self {
+        self.must_exist = value;
+        self
+    }
+
+    /// Set if opening fails when the file already exists, always creating a new file.
+    pub fn create_new(mut self, value: bool) -> Self {
+        self.create_new = value;
+        self
+    }
+
+    /// Set if an existing file is truncated to empty when opening.
+    pub fn truncate(mut self, value: bool) -> Self {
+        self.truncate = value;
+        self
+    }
+
+    /// Set if an advisory lock is taken on the file, for as long as it's open.
+    ///
+    /// Read-only files take a shared lock, other files take an exclusive lock.
+    /// This only protects against other processes that also lock the file.
+    /// If the file is already locked, opening fails, unless a `lock_timeout` is set.
+    pub fn lock(mut self, value: bool) -> Self {
+        self.lock = value;
+        self
+    }
+
+    /// Set how long to wait for a lock held by another process to be released.
+    ///
+    /// Waiting only makes sense when locking, so this also enables `lock`.
+    pub fn lock_timeout(mut self, value: Duration) -> Self {
+        self.lock = true;
+        self.lock_timeout = Some(value);
+        self
+    }
+
+    /// Set if writes are checked strictly.
+    ///
+    /// Writes to an explicit offset always have to be within the current length of the file.
+    /// In strict mode, they also have to be within regions this file allocated, by writes without
+    /// an offset.
+    /// Regions that already existed when opening aren't known to be allocated, so this is meant
+    /// for newly created files.
+    pub fn strict(mut self, value: bool) -> Self {
+        self.strict = value;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use daicon::{protocol::file, Executor};
+    use stewart::{Handler, Id};
+
+    use super::{open_file, open_system_file, SystemFileOptions};
+    use crate::{
+        test_util::{read, write, TempPath},
+        MappedFile,
+    };
+
+    fn open(path: &TempPath, options: SystemFileOptions) -> (Executor, Handler<file::Request>) {
+        let executor = Executor::default();
+        let file = executor
+            .with_world(|world| open_system_file(world, Id::none(), path.to_path_string(), options))
+            .unwrap();
+        (executor, file)
+    }
+
+    #[test]
+    fn read_past_end_is_short() {
+        let path = TempPath::with_data(b"abcdef");
+        let (executor, file) = open(&path, SystemFileOptions::default());
+
+        assert_eq!(read(&executor, &file, 0, 6).unwrap(), b"abcdef");
+        assert_eq!(read(&executor, &file, 4, 8).unwrap(), b"ef");
+        assert_eq!(read(&executor, &file, 16, 8).unwrap(), b"");
+    }
+
+    #[test]
+    fn write_region_must_exist() {
+        let path = TempPath::with_data(b"abcdef");
+        let (executor, file) = open(&path, SystemFileOptions::default());
+
+        assert!(matches!(
+            write(&executor, &file, Some(4), b"xyz"),
+            Err(file::Error::InvalidRegion)
+        ));
+        assert!(matches!(
+            write(&executor, &file, Some(u64::MAX), b"x"),
+            Err(file::Error::InvalidRegion)
+        ));
+
+        // Outside strict mode, any existing region can be written
+        assert_eq!(write(&executor, &file, Some(3), b"xyz").unwrap(), 3);
+        assert_eq!(std::fs::read(path.path()).unwrap(), b"abcxyz");
+    }
+
+    #[test]
+    fn strict_write_region_must_be_allocated() {
+        let path = TempPath::with_data(b"abcdef");
+        let (executor, file) = open(&path, SystemFileOptions::default().strict(true));
+
+        assert!(matches!(
+            write(&executor, &file, Some(0), b"x"),
+            Err(file::Error::InvalidRegion)
+        ));
+
+        // Appends are merged, so writes can span several of them, but not the region before
+        assert_eq!(write(&executor, &file, None, b"gh").unwrap(), 6);
+        assert_eq!(write(&executor, &file, None, b"ij").unwrap(), 8);
+        assert_eq!(write(&executor, &file, Some(7), b"HI").unwrap(), 7);
+        assert!(matches!(
+            write(&executor, &file, Some(5), b"FG"),
+            Err(file::Error::InvalidRegion)
+        ));
+
+        assert_eq!(std::fs::read(path.path()).unwrap(), b"abcdefgHIj");
+    }
+
+    #[test]
+    fn open_modes() {
+        let default = SystemFileOptions::default;
+
+        // Missing files are created, unless they must exist
+        let missing = TempPath::new();
+        assert!(open_file(missing.path(), &default().must_exist(true)).is_err());
+        assert!(open_file(missing.path(), &default().read_only(true)).is_err());
+        assert!(open_file(missing.path(), &default()).is_ok());
+        assert!(missing.path().exists());
+
+        // Existing files are kept, unless they must be new
+        let existing = TempPath::with_data(b"abcdef");
+        assert!(open_file(existing.path(), &default().create_new(true)).is_err());
+        assert!(open_file(existing.path(), &default().must_exist(true)).is_ok());
+        assert!(open_file(existing.path(), &default().read_only(true)).is_ok());
+        assert_eq!(std::fs::read(existing.path()).unwrap(), b"abcdef");
+
+        let new = TempPath::new();
+        assert!(open_file(new.path(), &default().create_new(true)).is_ok());
+        assert!(new.path().exists());
+
+        // Contradicting options
+        assert!(open_file(
+            existing.path(),
+            &default().must_exist(true).create_new(true)
+        )
+        .is_err());
+        assert!(open_file(existing.path(), &default().read_only(true).truncate(true)).is_err());
+        assert!(open_file(existing.path(), &default().read_only(true).create_new(true)).is_err());
+    }
+
+    #[test]
+    fn open_truncates() {
+        let path = TempPath::with_data(b"abcdef");
+        open_file(path.path(), &SystemFileOptions::default().truncate(true)).unwrap();
+        assert_eq!(std::fs::read(path.path()).unwrap(), b"");
+
+        // Truncating is delayed until locked, but still happens
+        let path = TempPath::with_data(b"abcdef");
+        let options = SystemFileOptions::default().truncate(true).lock(true);
+        open_file(path.path(), &options).unwrap();
+        assert_eq!(std::fs::read(path.path()).unwrap(), b"");
+    }
+
+    #[test]
+    fn read_only_rejects_writes() {
+        let path = TempPath::with_data(b"abcdef");
+        let (executor, file) = open(&path, SystemFileOptions::default().read_only(true));
+
+        assert_eq!(read(&executor, &file, 0, 2).unwrap(), b"ab");
+        assert!(matches!(
+            write(&executor, &file, None, b"gh"),
+            Err(file::Error::NotSupported)
+        ));
+        assert_eq!(std::fs::read(path.path()).unwrap(), b"abcdef");
+    }
+
+    #[test]
+    fn lock_excludes_other_opens() {
+        let path = TempPath::with_data(b"abcdef");
+        let exclusive = SystemFileOptions::default().lock(true);
+        let shared = SystemFileOptions::default().read_only(true).lock(true);
+
+        let file = open_file(path.path(), &exclusive).unwrap();
+        assert!(open_file(path.path(), &exclusive).is_err());
+        assert!(open_file(path.path(), &shared).is_err());
+        assert!(MappedFile::open_locked(path.path(), None).is_err());
+
+        // Setting a timeout locks too, rather than ignoring the lock
+        let timeout = SystemFileOptions::default().lock_timeout(Duration::from_millis(50));
+        assert!(open_file(path.path(), &timeout).is_err());
+
+        // Readers can share the file, but still keep out writers
+        drop(file);
+        let first = open_file(path.path(), &shared).unwrap();
+        let _second = MappedFile::open_locked(path.path(), None).unwrap();
+        assert!(open_file(path.path(), &exclusive).is_err());
+
+        drop(first);
+        assert!(open_file(path.path(), &exclusive).is_err());
+    }
+
+    #[test]
+    fn lock_timeout_waits_for_release() {
+        let path = TempPath::with_data(b"abcdef");
+        let options = SystemFileOptions::default().lock(true);
+
+        let file = open_file(path.path(), &options).unwrap();
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(file);
+        });
+
+        let start = Instant::now();
+        let options = options.lock_timeout(Duration::from_secs(10));
+        open_file(path.path(), &options).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(150));
+
+        release.join().unwrap();
+    }
+}
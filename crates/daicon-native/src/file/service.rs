@@ -0,0 +1,284 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context as _, Error};
+use daicon::protocol::file;
+use stewart::{Actor, Context, Handler, Id, World};
+use tracing::{event, instrument, Level};
+
+use crate::SystemFileOptions;
+
+#[instrument(skip_all)]
+pub fn open_system_file(
+    world: &mut World,
+    id: Id,
+    path: String,
+    options: SystemFileOptions,
+) -> Result<Handler<file::Request>, Error> {
+    event!(Level::INFO, "opening");
+
+    let file = open_file(Path::new(&path), &options)?;
+
+    let id = world.create(id, "daicon-system-file")?;
+
+    let actor = SystemFile {
+        file,
+        options,
+        allocated: Allocated::default(),
+    };
+    world.start(id, actor)?;
+
+    Ok(Handler::to(id))
+}
+
+struct SystemFile {
+    file: File,
+    options: SystemFileOptions,
+    /// Regions allocated by appending writes, only tracked in strict mode.
+    allocated: Allocated,
+}
+
+impl Actor for SystemFile {
+    type Message = file::Request;
+
+    fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+        while let Some(message) = cx.next() {
+            match message.action {
+                file::Action::Read(action) => {
+                    event!(Level::DEBUG, "reading from file");
+
+                    let result = self.read(action.offset, action.size);
+                    if let Err(error) = &result {
+                        event!(Level::WARN, ?error, "failed to read from file");
+                    }
+
+                    // Reply result
+                    let result = file::ReadResponse {
+                        id: message.id,
+                        result: result.map_err(file::Error::from),
+                    };
+                    action.on_result.handle(world, result);
+                }
+                file::Action::Write(action) => {
+                    event!(Level::DEBUG, "writing to file");
+
+                    let result = self.write(action.offset, &action.data);
+                    if let Err(error) = &result {
+                        event!(Level::WARN, ?error, "failed to write to file");
+                    }
+
+                    // Reply result
+                    let result = file::WriteResponse {
+                        id: message.id,
+                        result,
+                    };
+                    action.on_result.handle(world, result);
+                }
+                file::Action::Cancel(_) => {
+                    // Actions are completed immediately, so there's never anything to cancel
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SystemFile {
+    fn read(&mut self, offset: u64, size: u64) -> Result<Vec<u8>, io::Error> {
+        let mut data = vec![0u8; size as usize];
+
+        self.file.seek(SeekFrom::Start(offset))?;
+        let read = read_exact_eof(&mut self.file, &mut data)?;
+
+        // Reads past EOF are short
+        data.truncate(read);
+
+        Ok(data)
+    }
+
+    fn write(&mut self, offset: Option<u64>, data: &[u8]) -> Result<u64, file::Error> {
+        if self.options.read_only {
+            return Err(file::Error::NotSupported);
+        }
+
+        let append = offset.is_none();
+
+        // Seek to given location
+        let seek_from = match offset {
+            Some(offset) => {
+                self.check_region(offset, data.len() as u64)?;
+                SeekFrom::Start(offset)
+            }
+            None => SeekFrom::End(0),
+        };
+        self.file.seek(seek_from)?;
+        let offset = self.file.stream_position()?;
+
+        // Perform the write
+        self.file.write_all(data)?;
+
+        // Track what we've allocated
+        if append && self.options.strict {
+            self.allocated.insert(offset..offset + data.len() as u64);
+        }
+
+        Ok(offset)
+    }
+
+    /// Check the region is valid to write to.
+    fn check_region(&self, offset: u64, size: u64) -> Result<(), file::Error> {
+        let end = offset.checked_add(size).ok_or(file::Error::InvalidRegion)?;
+
+        // The region must already exist
+        if end > self.file.metadata()?.len() {
+            event!(Level::WARN, offset, size, "write region past end of file");
+            return Err(file::Error::InvalidRegion);
+        }
+
+        // In strict mode, the region must also be allocated, regions are merged so it has to be
+        // entirely within one
+        if self.options.strict && !self.allocated.contains(offset..end) {
+            event!(Level::WARN, offset, size, "write region not allocated");
+            return Err(file::Error::InvalidRegion);
+        }
+
+        Ok(())
+    }
+}
+
+/// Regions of a file allocated by appending writes, sorted and merged.
+#[derive(Default)]
+pub(crate) struct Allocated {
+    regions: Vec<Range<u64>>,
+}
+
+impl Allocated {
+    /// Track a newly allocated region, which has to come after all existing regions.
+    pub fn insert(&mut self, region: Range<u64>) {
+        // Appends always come after existing regions, so only the last can be merged with
+        match self.regions.last_mut() {
+            Some(last) if last.end == region.start => last.end = region.end,
+            _ => self.regions.push(region),
+        }
+    }
+
+    /// Check if a region is entirely allocated, regions are merged so it has to be entirely
+    /// within one.
+    pub fn contains(&self, region: Range<u64>) -> bool {
+        self.regions
+            .iter()
+            .any(|allocated| allocated.start <= region.start && region.end <= allocated.end)
+    }
+}
+
+/// Open a system file, and lock it, as configured by the options.
+pub(crate) fn open_file(path: &Path, options: &SystemFileOptions) -> Result<File, Error> {
+    let open_options = open_options(options)?;
+    let file = open_options
+        .open(path)
+        .with_context(|| format!("failed to open system file \"{}\"", path.display()))?;
+
+    if options.lock {
+        lock(&file, options)
+            .with_context(|| format!("failed to lock system file \"{}\"", path.display()))?;
+
+        // Truncating is delayed until we have the lock, to not pull the file out from under
+        // another process
+        if options.truncate {
+            file.set_len(0)?;
+        }
+    }
+
+    Ok(file)
+}
+
+impl Drop for SystemFile {
+    fn drop(&mut self) {
+        event!(Level::DEBUG, "closing system file");
+    }
+}
+
+fn open_options(options: &SystemFileOptions) -> Result<OpenOptions, Error> {
+    let mut open_options = OpenOptions::new();
+    open_options.read(true);
+
+    if options.read_only {
+        if options.create_new || options.truncate {
+            bail!("read-only system files can't be created or truncated");
+        }
+
+        return Ok(open_options);
+    }
+
+    if options.must_exist && options.create_new {
+        bail!("system files can't both be required to exist and be created new");
+    }
+
+    open_options
+        .write(true)
+        .create(!options.must_exist)
+        .create_new(options.create_new)
+        .truncate(options.truncate && !options.lock);
+
+    Ok(open_options)
+}
+
+fn lock(file: &File, options: &SystemFileOptions) -> Result<(), Error> {
+    let deadline = options.lock_timeout.map(|timeout| Instant::now() + timeout);
+    let mut delay = Duration::from_millis(10);
+
+    loop {
+        let result = if options.read_only {
+            file.try_lock_shared()
+        } else {
+            file.try_lock()
+        };
+
+        match result {
+            Ok(()) => return Ok(()),
+            Err(TryLockError::Error(error)) => return Err(error.into()),
+            Err(TryLockError::WouldBlock) => {}
+        }
+
+        // Someone else has the lock, wait for it to be released if we're allowed to
+        let now = Instant::now();
+        let remaining = deadline.and_then(|deadline| deadline.checked_duration_since(now));
+        let Some(remaining) = remaining.filter(|remaining| !remaining.is_zero()) else {
+            bail!("system file is locked by another process");
+        };
+
+        event!(Level::DEBUG, "waiting for lock on system file");
+        thread::sleep(delay.min(remaining));
+        delay = (delay * 2).min(Duration::from_millis(500));
+    }
+}
+
+/// Copy of read_exact except allowing for EOF, returning the amount of bytes read.
+fn read_exact_eof(file: &mut File, mut buf: &mut [u8]) -> Result<usize, io::Error> {
+    let mut read = 0;
+
+    while !buf.is_empty() {
+        match file.read(buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                let tmp = buf;
+                buf = &mut tmp[n..];
+                read += n;
+            }
+            Err(error) => match error.kind() {
+                ErrorKind::Interrupted => {}
+                ErrorKind::UnexpectedEof => break,
+                _ => return Err(error),
+            },
+        }
+    }
+
+    Ok(read)
+}
@@ -1,3 +1,13 @@
 mod file;
+mod mmap;
+mod pooled;
+#[cfg(test)]
+mod test_util;
+mod threaded;
 
-pub use self::file::open_system_file;
+pub use self::{
+    file::{open_system_file, SystemFileOptions},
+    mmap::{open_mmap_file, MappedFile, MappedSlice},
+    pooled::{open_pooled_file, PooledFileOptions},
+    threaded::{open_threaded_source, ThreadedSource},
+};
@@ -0,0 +1,287 @@
+use std::{
+    fs::File,
+    ops::{Deref, Range},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context as _, Error};
+use daicon::protocol::file;
+use daicon_types::TableRef;
+use memmap2::Mmap;
+use stewart::{Actor, Context, Handler, Id, World};
+use tracing::{event, instrument, Level};
+
+use crate::{file::open_file, SystemFileOptions};
+
+/// Read-only memory-mapped system file.
+///
+/// Cloning is cheap, all clones share the same mapping.
+/// Besides answering `file` reads through `open_mmap_file`, regions can be borrowed directly, or
+/// shared as `MappedSlice` without copying.
+///
+/// The file must not be modified or truncated while mapped, other processes included.
+/// `open_locked` guards against other processes that lock the file too.
+#[derive(Clone)]
+pub struct MappedFile {
+    map: Arc<Mapping>,
+}
+
+struct Mapping {
+    map: Mmap,
+    /// Kept open, so a lock taken on it is held for as long as the mapping is alive.
+    _file: File,
+}
+
+impl Deref for Mapping {
+    type Target = Mmap;
+
+    fn deref(&self) -> &Mmap {
+        &self.map
+    }
+}
+
+impl MappedFile {
+    /// Map the file at the given path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path).context("failed to open system file for mapping")?;
+        Self::map(file)
+    }
+
+    /// Map the file at the given path, holding a shared advisory lock until the mapping and all
+    /// slices of it are dropped.
+    ///
+    /// Other processes that lock the file, such as `open_system_file` with `lock`, can't write to
+    /// it while mapped.
+    /// If the file is already locked for writing, opening fails, unless a `lock_timeout` is set.
+    pub fn open_locked(
+        path: impl AsRef<Path>,
+        lock_timeout: Option<Duration>,
+    ) -> Result<Self, Error> {
+        let mut options = SystemFileOptions::default().read_only(true).lock(true);
+        options.lock_timeout = lock_timeout;
+
+        let file = open_file(path.as_ref(), &options)?;
+        Self::map(file)
+    }
+
+    fn map(file: File) -> Result<Self, Error> {
+        // Safety: Modifying the file while mapped is undefined behavior, we can't prevent this
+        // so it's part of the documented contract of `MappedFile`.
+        let map = unsafe { Mmap::map(&file) }.context("failed to map system file")?;
+
+        let mapping = Mapping { map, _file: file };
+        Ok(Self {
+            map: Arc::new(mapping),
+        })
+    }
+
+    /// Get the size of the mapped file in bytes.
+    pub fn len(&self) -> u64 {
+        self.map.len() as u64
+    }
+
+    /// Returns true if the mapped file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Borrow a region of the file, `None` if the region is out of bounds.
+    pub fn get(&self, offset: u64, size: u64) -> Option<&[u8]> {
+        self.map.get(self.range(offset, size)?)
+    }
+
+    /// Get a region of the file as a shared slice, `None` if the region is out of bounds.
+    pub fn slice(&self, offset: u64, size: u64) -> Option<MappedSlice> {
+        let range = self.range(offset, size)?;
+
+        let slice = MappedSlice {
+            map: self.map.clone(),
+            range,
+        };
+        Some(slice)
+    }
+
+    /// Parse the table at the given offset in-place, `None` if it's out of bounds or misaligned.
+    pub fn table(&self, offset: u64) -> Option<TableRef<'_>> {
+        let offset = usize::try_from(offset).ok()?;
+        TableRef::parse(self.map.get(offset..)?)
+    }
+
+    fn range(&self, offset: u64, size: u64) -> Option<Range<usize>> {
+        let start = usize::try_from(offset).ok()?;
+        let end = start.checked_add(usize::try_from(size).ok()?)?;
+
+        if end > self.map.len() {
+            return None;
+        }
+
+        Some(start..end)
+    }
+}
+
+/// Shared slice of a `MappedFile`, keeping the mapping alive.
+#[derive(Clone)]
+pub struct MappedSlice {
+    map: Arc<Mapping>,
+    range: Range<usize>,
+}
+
+impl Deref for MappedSlice {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for MappedSlice {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Open a memory-mapped file as a read-only `file`.
+///
+/// Reads are answered by copying from the mapping, writes respond with `Error::NotSupported`.
+#[instrument(skip_all)]
+pub fn open_mmap_file(
+    world: &mut World,
+    id: Id,
+    file: MappedFile,
+) -> Result<Handler<file::Request>, Error> {
+    event!(Level::INFO, "opening");
+
+    let id = world.create(id, "daicon-mmap-file")?;
+
+    let actor = MmapFile { file };
+    world.start(id, actor)?;
+
+    Ok(Handler::to(id))
+}
+
+struct MmapFile {
+    file: MappedFile,
+}
+
+impl Actor for MmapFile {
+    type Message = file::Request;
+
+    fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+        while let Some(message) = cx.next() {
+            match message.action {
+                file::Action::Read(action) => {
+                    event!(Level::DEBUG, "reading from mapped file");
+
+                    // Reads past EOF are short
+                    let available = self.file.len().saturating_sub(action.offset);
+                    let size = action.size.min(available);
+                    let data = self
+                        .file
+                        .get(action.offset, size)
+                        .map(<[u8]>::to_vec)
+                        .unwrap_or_default();
+
+                    // Reply result
+                    let result = file::ReadResponse {
+                        id: message.id,
+                        result: Ok(data),
+                    };
+                    action.on_result.handle(world, result);
+                }
+                file::Action::Write(action) => {
+                    let result = file::WriteResponse {
+                        id: message.id,
+                        result: Err(file::Error::NotSupported),
+                    };
+                    action.on_result.handle(world, result);
+                }
+                file::Action::Cancel(_) => {
+                    // Actions are completed immediately, so there's never anything to cancel
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::bytes_of;
+    use daicon::{protocol::file, Executor};
+    use daicon_types::{Header, Id, Index};
+    use stewart::Id as ActorId;
+
+    use super::{open_mmap_file, MappedFile};
+    use crate::test_util::{read, write, TempPath};
+
+    fn package() -> Vec<u8> {
+        let mut header = Header::default();
+        header.set_capacity(1);
+        header.set_valid(1);
+        header.set_offset(36);
+
+        let mut index = Index::default();
+        index.set_id(Id(1));
+        index.set_size(4);
+
+        let mut data = bytes_of(&header).to_vec();
+        data.extend_from_slice(bytes_of(&index));
+        data.extend_from_slice(b"data");
+        data
+    }
+
+    #[test]
+    fn table_parses_in_place() {
+        let path = TempPath::with_data(&package());
+        let file = MappedFile::open(path.path()).unwrap();
+
+        let table = file.table(0).unwrap();
+        assert!(table.header().is_valid());
+        assert_eq!(table.find(Id(1)).map(Index::size), Some(4));
+
+        // Misaligned, or not enough room for a header
+        assert!(file.table(1).is_none());
+        assert!(file.table(32).is_none());
+        assert!(file.table(u64::MAX).is_none());
+    }
+
+    #[test]
+    fn get_out_of_range_is_none() {
+        let path = TempPath::with_data(&package());
+        let file = MappedFile::open(path.path()).unwrap();
+
+        assert_eq!(file.len(), 40);
+        assert_eq!(file.get(36, 4), Some(&b"data"[..]));
+        assert_eq!(file.get(40, 0), Some(&[][..]));
+        assert!(file.get(36, 5).is_none());
+        assert!(file.get(41, 0).is_none());
+        assert!(file.get(u64::MAX, 2).is_none());
+        assert!(file.slice(36, 5).is_none());
+
+        // Slices keep the mapping alive on their own
+        let slice = file.slice(36, 4).unwrap();
+        drop(file);
+        assert_eq!(&*slice, b"data");
+    }
+
+    #[test]
+    fn mmap_file_reads_short_and_rejects_writes() {
+        let path = TempPath::with_data(&package());
+        let mapped = MappedFile::open(path.path()).unwrap();
+
+        let executor = Executor::default();
+        let file = executor
+            .with_world(|world| open_mmap_file(world, ActorId::none(), mapped))
+            .unwrap();
+
+        assert_eq!(read(&executor, &file, 36, 8).unwrap(), b"data");
+        assert_eq!(read(&executor, &file, 64, 8).unwrap(), b"");
+        assert!(matches!(
+            write(&executor, &file, None, b"more"),
+            Err(file::Error::NotSupported)
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+mod service;
+mod workers;
+
+pub use self::service::open_pooled_file;
+
+/// Additional options for opening a pooled file.
+#[derive(Debug, Clone)]
+pub struct PooledFileOptions {
+    threads: usize,
+}
+
+impl PooledFileOptions {
+    /// Set the amount of I/O threads, and with that the amount of actions that can be performed at
+    /// the same time.
+    ///
+    /// At least one thread is always started.
+    pub fn threads(mut self, value: usize) -> Self {
+        self.threads = value;
+        self
+    }
+}
+
+impl Default for PooledFileOptions {
+    fn default() -> Self {
+        Self { threads: 4 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use daicon::{protocol::file, Executor};
+    use stewart::{Handler, Id};
+
+    use super::{open_pooled_file, PooledFileOptions};
+    use crate::{
+        test_util::{read, write, TempPath},
+        SystemFileOptions,
+    };
+
+    fn open(path: &TempPath, options: SystemFileOptions) -> (Executor, Handler<file::Request>) {
+        let executor = Executor::default();
+        let file = executor
+            .with_world(|world| {
+                open_pooled_file(
+                    world,
+                    Id::none(),
+                    &executor,
+                    path.to_path_string(),
+                    options,
+                    PooledFileOptions::default(),
+                )
+            })
+            .unwrap();
+        (executor, file)
+    }
+
+    #[test]
+    fn read_past_end_is_short() {
+        let path = TempPath::with_data(b"abcdef");
+        let (executor, file) = open(&path, SystemFileOptions::default());
+
+        assert_eq!(read(&executor, &file, 0, 6).unwrap(), b"abcdef");
+        assert_eq!(read(&executor, &file, 4, 8).unwrap(), b"ef");
+        assert_eq!(read(&executor, &file, 16, 8).unwrap(), b"");
+    }
+
+    #[test]
+    fn appends_do_not_overlap() {
+        let path = TempPath::with_data(b"abcdef");
+        let (executor, file) = open(&path, SystemFileOptions::default());
+
+        assert_eq!(write(&executor, &file, None, b"gh").unwrap(), 6);
+        assert_eq!(write(&executor, &file, None, b"ij").unwrap(), 8);
+        assert_eq!(write(&executor, &file, Some(0), b"AB").unwrap(), 0);
+
+        assert_eq!(std::fs::read(path.path()).unwrap(), b"ABcdefghij");
+    }
+
+    #[test]
+    fn write_region_must_be_valid() {
+        let path = TempPath::with_data(b"abcdef");
+        let (executor, file) = open(&path, SystemFileOptions::default().strict(true));
+
+        // Past the end of the file
+        assert!(matches!(
+            write(&executor, &file, Some(4), b"xyz"),
+            Err(file::Error::InvalidRegion)
+        ));
+
+        // Within the file, but not allocated by this file
+        assert!(matches!(
+            write(&executor, &file, Some(0), b"x"),
+            Err(file::Error::InvalidRegion)
+        ));
+
+        // Allocated, including through merged appends
+        assert_eq!(write(&executor, &file, None, b"gh").unwrap(), 6);
+        assert_eq!(write(&executor, &file, None, b"ij").unwrap(), 8);
+        assert_eq!(write(&executor, &file, Some(7), b"HI").unwrap(), 7);
+    }
+
+    #[test]
+    fn read_only_rejects_writes() {
+        let path = TempPath::with_data(b"abcdef");
+        let (executor, file) = open(&path, SystemFileOptions::default().read_only(true));
+
+        assert_eq!(read(&executor, &file, 0, 2).unwrap(), b"ab");
+        assert!(matches!(
+            write(&executor, &file, None, b"gh"),
+            Err(file::Error::NotSupported)
+        ));
+    }
+}
@@ -0,0 +1,223 @@
+use std::{collections::HashMap, fs::File, io, path::Path, sync::Arc};
+
+use anyhow::Error;
+use daicon::{protocol::file, Executor, RemoteSender};
+use stewart::{Actor, Context, Handler, Id, World};
+use tracing::{event, instrument, Level};
+use uuid::Uuid;
+
+use crate::{
+    file::{open_file, Allocated},
+    pooled::{
+        workers::{self, Workers},
+        PooledFileOptions,
+    },
+    SystemFileOptions,
+};
+
+/// Open a system file, performing reads and writes on a pool of I/O threads.
+///
+/// Unlike `open_system_file`, this doesn't block the world while waiting on the disk, and many
+/// actions can be in flight at the same time.
+/// Completions are handed back to the world through `executor`, which has to be run for them to
+/// arrive.
+/// The file is opened, locked, and checked the same way as by `open_system_file`.
+#[instrument(skip_all)]
+pub fn open_pooled_file(
+    world: &mut World,
+    id: Id,
+    executor: &Executor,
+    path: String,
+    file_options: SystemFileOptions,
+    options: PooledFileOptions,
+) -> Result<Handler<file::Request>, Error> {
+    event!(Level::INFO, "opening");
+
+    let file = open_file(Path::new(&path), &file_options)?;
+    let end = file.metadata()?.len();
+
+    let id = world.create(id, "daicon-pooled-file")?;
+    let handler = Handler::to(id);
+
+    let actor = PooledFile {
+        file: Arc::new(file),
+        workers: Workers::new(options.threads)?,
+        remote: executor.remote(handler.clone().map(Message::Completion)),
+        options: file_options,
+        end,
+        allocated: Allocated::default(),
+
+        reads: HashMap::new(),
+        writes: HashMap::new(),
+    };
+    world.start(id, actor)?;
+
+    Ok(handler.map(Message::Request))
+}
+
+struct PooledFile {
+    file: Arc<File>,
+    workers: Workers,
+    remote: RemoteSender<Completion>,
+    options: SystemFileOptions,
+    /// End of the file, including appends still in flight.
+    end: u64,
+    /// Regions allocated by appending writes, only tracked in strict mode.
+    allocated: Allocated,
+
+    // Ongoing tracked actions
+    reads: HashMap<Uuid, Handler<file::ReadResponse>>,
+    writes: HashMap<Uuid, Handler<file::WriteResponse>>,
+}
+
+enum Message {
+    Request(file::Request),
+    Completion(Completion),
+}
+
+enum Completion {
+    Read {
+        id: Uuid,
+        result: Result<Vec<u8>, io::Error>,
+    },
+    Write {
+        id: Uuid,
+        result: Result<u64, io::Error>,
+    },
+}
+
+impl Actor for PooledFile {
+    type Message = Message;
+
+    fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+        while let Some(message) = cx.next() {
+            match message {
+                Message::Request(request) => self.on_request(world, request),
+                Message::Completion(completion) => self.on_completion(world, completion),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PooledFile {
+    fn on_request(&mut self, world: &mut World, request: file::Request) {
+        let id = request.id;
+
+        match request.action {
+            file::Action::Read(action) => {
+                event!(Level::DEBUG, "queueing file read");
+
+                self.reads.insert(id, action.on_result);
+                self.spawn(move |file| {
+                    // Reads past EOF are short
+                    let mut data = vec![0u8; action.size as usize];
+                    let result = workers::read_at(file, action.offset, &mut data).map(|read| {
+                        data.truncate(read);
+                        data
+                    });
+                    Completion::Read { id, result }
+                });
+            }
+            file::Action::Write(action) => {
+                event!(Level::DEBUG, "queueing file write");
+
+                let size = action.data.len() as u64;
+                if let Err(error) = self.check_write(action.offset, size) {
+                    let response = file::WriteResponse {
+                        id,
+                        result: Err(error),
+                    };
+                    action.on_result.handle(world, response);
+                    return;
+                }
+
+                // Allocate the region here rather than on the I/O threads, so concurrent appends
+                // don't overlap
+                let offset = action.offset.unwrap_or(self.end);
+                self.end = self.end.max(offset + size);
+                if action.offset.is_none() && self.options.strict {
+                    self.allocated.insert(offset..offset + size);
+                }
+
+                self.writes.insert(id, action.on_result);
+                let data = action.data;
+                self.spawn(move |file| {
+                    let result = workers::write_at(file, offset, &data).map(|_| offset);
+                    Completion::Write { id, result }
+                });
+            }
+            file::Action::Cancel(action) => {
+                // Writes may already be partially done, so only reads can be cancelled
+                if let Some(on_result) = self.reads.remove(&action.id) {
+                    event!(Level::DEBUG, id = ?action.id, "cancelled file read");
+
+                    let response = file::ReadResponse {
+                        id: action.id,
+                        result: Err(file::Error::Cancelled),
+                    };
+                    on_result.handle(world, response);
+                }
+            }
+        }
+    }
+
+    /// Check a write is allowed, and within a valid region.
+    fn check_write(&self, offset: Option<u64>, size: u64) -> Result<(), file::Error> {
+        if self.options.read_only {
+            return Err(file::Error::NotSupported);
+        }
+
+        // Writes to an explicit offset have to be within the file already
+        let Some(offset) = offset else {
+            return Ok(());
+        };
+        let end = offset.checked_add(size).ok_or(file::Error::InvalidRegion)?;
+        if end > self.end {
+            event!(Level::WARN, offset, size, "write region past end of file");
+            return Err(file::Error::InvalidRegion);
+        }
+
+        // In strict mode, the region must also be allocated
+        if self.options.strict && !self.allocated.contains(offset..end) {
+            event!(Level::WARN, offset, size, "write region not allocated");
+            return Err(file::Error::InvalidRegion);
+        }
+
+        Ok(())
+    }
+
+    fn on_completion(&mut self, world: &mut World, completion: Completion) {
+        // If there's no tracked action it was cancelled, so there's nothing left to do
+        match completion {
+            Completion::Read { id, result } => {
+                if let Some(on_result) = self.reads.remove(&id) {
+                    let result = result.map_err(file::Error::from);
+                    on_result.handle(world, file::ReadResponse { id, result });
+                }
+            }
+            Completion::Write { id, result } => {
+                if let Some(on_result) = self.writes.remove(&id) {
+                    let result = result.map_err(file::Error::from);
+                    on_result.handle(world, file::WriteResponse { id, result });
+                }
+            }
+        }
+    }
+
+    fn spawn<F>(&self, job: F)
+    where
+        F: FnOnce(&File) -> Completion + Send + 'static,
+    {
+        let file = self.file.clone();
+        let remote = self.remote.clone();
+
+        self.workers.spawn(Box::new(move || {
+            let completion = job(&file);
+
+            // If the executor is gone, there's nobody left to receive this
+            let _ = remote.send(completion);
+        }));
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs::File,
+    io::{self, ErrorKind},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use anyhow::{Context as _, Error};
+
+pub type Job = Box<dyn FnOnce() + Send>;
+
+/// Pool of threads performing blocking jobs.
+///
+/// Threads stop when the pool is dropped, after finishing already queued jobs.
+pub struct Workers {
+    sender: mpsc::Sender<Job>,
+}
+
+impl Workers {
+    pub fn new(threads: usize) -> Result<Self, Error> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("daicon-io-{}", i))
+                .spawn(move || run(&receiver))
+                .context("failed to spawn I/O thread")?;
+        }
+
+        Ok(Self { sender })
+    }
+
+    pub fn spawn(&self, job: Job) {
+        // Threads only stop when the sender is dropped, so this can't fail
+        let _ = self.sender.send(job);
+    }
+}
+
+fn run(receiver: &Mutex<mpsc::Receiver<Job>>) {
+    loop {
+        // Only hold the lock while receiving, so other threads can pick up jobs while we work
+        let job = receiver.lock().unwrap().recv();
+        let Ok(job) = job else {
+            break;
+        };
+
+        job();
+    }
+}
+
+/// Read at an offset, until `buf` is full or EOF is reached, returning the amount of bytes read.
+pub fn read_at(file: &File, mut offset: u64, mut buf: &mut [u8]) -> Result<usize, io::Error> {
+    let mut read = 0;
+
+    while !buf.is_empty() {
+        match read_at_once(file, offset, buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                let tmp = buf;
+                buf = &mut tmp[n..];
+                offset += n as u64;
+                read += n;
+            }
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(read)
+}
+
+/// Write all of `buf` at an offset.
+pub fn write_at(file: &File, mut offset: u64, mut buf: &[u8]) -> Result<(), io::Error> {
+    while !buf.is_empty() {
+        match write_at_once(file, offset, buf) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => {
+                buf = &buf[n..];
+                offset += n as u64;
+            }
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_at_once(file: &File, offset: u64, buf: &mut [u8]) -> Result<usize, io::Error> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(unix)]
+fn write_at_once(file: &File, offset: u64, buf: &[u8]) -> Result<usize, io::Error> {
+    std::os::unix::fs::FileExt::write_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at_once(file: &File, offset: u64, buf: &mut [u8]) -> Result<usize, io::Error> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn write_at_once(file: &File, offset: u64, buf: &[u8]) -> Result<usize, io::Error> {
+    std::os::windows::fs::FileExt::seek_write(file, buf, offset)
+}
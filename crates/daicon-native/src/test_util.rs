@@ -0,0 +1,104 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use daicon::{protocol::file, Executor};
+use stewart::Handler;
+use uuid::Uuid;
+
+/// Path to a file in the temporary directory, removed again when dropped.
+pub struct TempPath {
+    path: PathBuf,
+}
+
+impl TempPath {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("daicon-native-{}", Uuid::new_v4()));
+        Self { path }
+    }
+
+    /// Create the file with the given contents.
+    pub fn with_data(data: &[u8]) -> Self {
+        let path = Self::new();
+        std::fs::write(path.path(), data).unwrap();
+        path
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the path as a string, as the file opening functions take it.
+    pub fn to_path_string(&self) -> String {
+        self.path.to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Read from a file, running the executor until it responds.
+pub fn read(
+    executor: &Executor,
+    file: &Handler<file::Request>,
+    offset: u64,
+    size: u64,
+) -> Result<Vec<u8>, file::Error> {
+    let response = request(executor, file, |on_result| {
+        file::Action::Read(file::ReadAction {
+            offset,
+            size,
+            on_result,
+        })
+    });
+    response.result
+}
+
+/// Write to a file, running the executor until it responds.
+pub fn write(
+    executor: &Executor,
+    file: &Handler<file::Request>,
+    offset: Option<u64>,
+    data: &[u8],
+) -> Result<u64, file::Error> {
+    let response = request(executor, file, |on_result| {
+        file::Action::Write(file::WriteAction {
+            offset,
+            data: data.to_vec(),
+            on_result,
+        })
+    });
+    response.result
+}
+
+fn request<R: 'static>(
+    executor: &Executor,
+    file: &Handler<file::Request>,
+    action: impl FnOnce(Handler<R>) -> file::Action,
+) -> R {
+    let result = Rc::new(RefCell::new(None));
+    let result_ref = result.clone();
+    let on_result =
+        Handler::none().map(move |response: R| *result_ref.borrow_mut() = Some(response));
+
+    let request = file::Request {
+        id: Uuid::new_v4(),
+        action: action(on_result),
+    };
+    executor.send(file.clone(), request);
+
+    // Files on other threads respond through the executor's remotes
+    loop {
+        if let Some(response) = result.borrow_mut().take() {
+            return response;
+        }
+
+        executor.wait();
+        executor.run_until_idle().unwrap();
+    }
+}
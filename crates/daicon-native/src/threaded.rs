@@ -0,0 +1,377 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    rc::Rc,
+    sync::{mpsc, Arc},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::{anyhow, Context as _, Error};
+use daicon::{
+    open_file_source,
+    protocol::source::{self, Id, Stat},
+    Executor, FileSourceOptions, RemoteSender, SourceClient,
+};
+use futures::{
+    channel::oneshot,
+    executor::{block_on, LocalPool, LocalSpawner},
+    future::LocalBoxFuture,
+    task::LocalSpawnExt,
+    FutureExt,
+};
+use stewart::{Actor, Context, Handler, World};
+use tracing::{event, instrument, Level};
+
+use crate::{open_system_file, SystemFileOptions};
+
+/// Open a system file as a daicon source, running on its own thread.
+///
+/// The world, the system file, and the file source all live on a dedicated runtime thread.
+/// The returned handle forwards requests to that thread, and can be shared between threads.
+/// The runtime thread stops when the last handle is dropped, after finishing all requests made
+/// through handles.
+#[instrument(skip_all)]
+pub fn open_threaded_source(
+    path: String,
+    file_options: SystemFileOptions,
+    options: FileSourceOptions,
+) -> Result<ThreadedSource, Error> {
+    event!(Level::INFO, "starting source thread");
+
+    let (open_sender, open_receiver) = mpsc::channel();
+    let thread = thread::Builder::new()
+        .name("daicon-source".to_string())
+        .spawn(move || run(path, file_options, options, open_sender))
+        .context("failed to spawn source thread")?;
+
+    // Wait for the runtime to be ready, or report why it couldn't open
+    let sender = open_receiver
+        .recv()
+        .map_err(|_| anyhow!("source thread stopped before opening"))??;
+
+    let inner = Inner {
+        sender,
+        thread: Some(thread),
+    };
+    Ok(ThreadedSource {
+        inner: Arc::new(inner),
+    })
+}
+
+/// Thread-safe handle to a source running on its own thread.
+///
+/// Every method has an async variant, and a blocking `_blocking` variant.
+/// The async variants don't depend on any specific async runtime.
+#[derive(Clone)]
+pub struct ThreadedSource {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    sender: RemoteSender<Command>,
+    thread: Option<JoinHandle<()>>,
+}
+
+enum Command {
+    Task(Task),
+    /// A task spawned by the runtime completed.
+    TaskDone,
+    /// Stop, once all tasks are done.
+    Stop,
+}
+
+type Task = Box<dyn FnOnce(&SourceClient) -> LocalBoxFuture<'static, ()> + Send>;
+
+impl ThreadedSource {
+    /// Get the data associated with an ID.
+    pub fn get(&self, id: Id) -> impl Future<Output = Result<Vec<u8>, source::Error>> + Send {
+        self.request(move |client| client.get(id))
+    }
+
+    /// Set the data associated with an ID.
+    pub fn set(
+        &self,
+        id: Id,
+        data: Vec<u8>,
+    ) -> impl Future<Output = Result<(), source::Error>> + Send {
+        self.request(move |client| client.set(id, data))
+    }
+
+    /// Get a list of all IDs in the source.
+    pub fn list(&self) -> impl Future<Output = Result<Vec<Id>, source::Error>> + Send {
+        self.request(|client| client.list())
+    }
+
+    /// Get metadata of the data associated with an ID, without getting the data itself.
+    pub fn stat(&self, id: Id) -> impl Future<Output = Result<Stat, source::Error>> + Send {
+        self.request(move |client| client.stat(id))
+    }
+
+    /// Remove the data associated with an ID.
+    pub fn remove(&self, id: Id) -> impl Future<Output = Result<(), source::Error>> + Send {
+        self.request(move |client| client.remove(id))
+    }
+
+    /// Blocking variant of `get`.
+    pub fn get_blocking(&self, id: Id) -> Result<Vec<u8>, source::Error> {
+        block_on(self.get(id))
+    }
+
+    /// Blocking variant of `set`.
+    pub fn set_blocking(&self, id: Id, data: Vec<u8>) -> Result<(), source::Error> {
+        block_on(self.set(id, data))
+    }
+
+    /// Blocking variant of `list`.
+    pub fn list_blocking(&self) -> Result<Vec<Id>, source::Error> {
+        block_on(self.list())
+    }
+
+    /// Blocking variant of `stat`.
+    pub fn stat_blocking(&self, id: Id) -> Result<Stat, source::Error> {
+        block_on(self.stat(id))
+    }
+
+    /// Blocking variant of `remove`.
+    pub fn remove_blocking(&self, id: Id) -> Result<(), source::Error> {
+        block_on(self.remove(id))
+    }
+
+    /// Run a client call on the runtime thread, and receive its result through a future.
+    fn request<T, F, R>(&self, call: F) -> impl Future<Output = Result<T, source::Error>> + Send
+    where
+        T: Send + 'static,
+        F: FnOnce(&SourceClient) -> R + Send + 'static,
+        R: Future<Output = Result<T, source::Error>> + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        // The call is made on the runtime thread, so the request is sent from there
+        let task: Task = Box::new(move |client| {
+            let future = call(client);
+            async move {
+                let _ = sender.send(future.await);
+            }
+            .boxed_local()
+        });
+
+        // If the thread is gone the task is dropped, which the receiver reports below
+        let _ = self.inner.sender.send(Command::Task(task));
+
+        async move {
+            receiver.await.map_err(|_| source::Error::InternalError {
+                error: "source thread stopped".to_string(),
+            })?
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Command::Stop);
+
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+
+        // If the last handle is dropped by a task on the runtime thread, it can't wait for itself,
+        // the thread still stops on its own once its tasks are done
+        if thread.thread().id() == thread::current().id() {
+            event!(Level::DEBUG, "last handle dropped on source thread");
+            return;
+        }
+
+        // Wait for the thread, so all requests have completed, and with that their changes have
+        // been written, by the time the last handle is gone
+        if thread.join().is_err() {
+            event!(Level::ERROR, "source thread panicked");
+        }
+    }
+}
+
+fn run(
+    path: String,
+    file_options: SystemFileOptions,
+    options: FileSourceOptions,
+    open_sender: mpsc::Sender<Result<RemoteSender<Command>, Error>>,
+) {
+    let executor = Executor::default();
+    let mut pool = LocalPool::new();
+    let stopped = Rc::new(Cell::new(false));
+
+    // Open the source on this thread, under a runtime actor that receives commands
+    let result = executor.with_world(|world| {
+        let id = world.create(stewart::Id::none(), "daicon-threaded-source")?;
+        let file = open_system_file(world, id, path, file_options)?;
+        let source = open_file_source(world, id, file, options)?;
+
+        let handler = Handler::to(id);
+        let actor = Runtime {
+            client: SourceClient::new(executor.clone(), source),
+            executor: executor.clone(),
+            handler: handler.clone(),
+            spawner: pool.spawner(),
+            pending: 0,
+            stopping: false,
+            stopped: stopped.clone(),
+        };
+        world.start(id, actor)?;
+
+        Ok(handler)
+    });
+    let handler = match result {
+        Ok(handler) => handler,
+        Err(error) => {
+            // The world removes the runtime actor that never got started
+            let _ = executor.run_until_idle();
+
+            let _ = open_sender.send(Err(error));
+            return;
+        }
+    };
+
+    // Receive commands from other threads through the executor
+    let _ = open_sender.send(Ok(executor.remote(handler)));
+
+    event!(Level::DEBUG, "source thread running");
+
+    loop {
+        if let Err(error) = executor.run_until_idle() {
+            event!(Level::ERROR, ?error, "failed to run executor");
+            break;
+        }
+
+        // Responses have been handled by the world, so let tasks pass them on
+        pool.run_until_stalled();
+
+        if stopped.get() {
+            break;
+        }
+
+        executor.wait();
+    }
+
+    event!(Level::DEBUG, "source thread stopped");
+}
+
+struct Runtime {
+    client: SourceClient,
+    executor: Executor,
+    handler: Handler<Command>,
+    spawner: LocalSpawner,
+    /// Amount of spawned tasks that haven't completed yet.
+    pending: usize,
+    stopping: bool,
+    stopped: Rc<Cell<bool>>,
+}
+
+impl Actor for Runtime {
+    type Message = Command;
+
+    fn process(&mut self, _world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+        while let Some(command) = cx.next() {
+            match command {
+                Command::Task(task) => self.spawn(task),
+                Command::TaskDone => self.pending -= 1,
+                Command::Stop => self.stopping = true,
+            }
+        }
+
+        // Requests still in flight would be dropped, so only stop once they're done
+        if self.stopping && self.pending == 0 {
+            // Stopping also stops the file and source, and drops the client, which refers back to
+            // the executor, so the world can be dropped
+            self.stopped.set(true);
+            cx.stop();
+        }
+
+        Ok(())
+    }
+}
+
+impl Runtime {
+    fn spawn(&mut self, task: Task) {
+        let future = task(&self.client);
+
+        // Report back when done, so we know when it's safe to stop
+        let executor = self.executor.clone();
+        let handler = self.handler.clone();
+        let task = async move {
+            future.await;
+            executor.send(handler, Command::TaskDone);
+        };
+
+        match self.spawner.spawn_local(task) {
+            Ok(()) => self.pending += 1,
+            Err(error) => event!(Level::ERROR, ?error, "failed to spawn source task"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use daicon::{protocol::source::Id, FileSourceOptions};
+    use futures::FutureExt;
+
+    use super::{open_threaded_source, Command, Task, ThreadedSource};
+    use crate::{test_util::TempPath, SystemFileOptions};
+
+    fn open(
+        path: &TempPath,
+        file_options: SystemFileOptions,
+        options: FileSourceOptions,
+    ) -> ThreadedSource {
+        open_threaded_source(path.to_path_string(), file_options, options).unwrap()
+    }
+
+    #[test]
+    fn requests_finish_before_stopping() {
+        let path = TempPath::new();
+        let source = open(
+            &path,
+            SystemFileOptions::default(),
+            FileSourceOptions::default(),
+        );
+
+        // Nothing waits for these, so they're still in flight when the last handle is dropped
+        for id in 0..16 {
+            drop(source.set(Id(id), vec![id as u8; 64]));
+        }
+        drop(source);
+
+        let source = open(
+            &path,
+            SystemFileOptions::default().must_exist(true),
+            FileSourceOptions::default().open_table(0),
+        );
+        let mut ids = source.list_blocking().unwrap();
+        ids.sort_unstable_by_key(|id| id.0);
+        assert_eq!(ids, (0..16).map(Id).collect::<Vec<_>>());
+        assert_eq!(source.get_blocking(Id(15)).unwrap(), vec![15; 64]);
+    }
+
+    #[test]
+    fn last_handle_dropped_on_source_thread() {
+        let path = TempPath::new();
+        let source = open(
+            &path,
+            SystemFileOptions::default().lock(true),
+            FileSourceOptions::default(),
+        );
+
+        // Hand the only handle to the source thread, and drop it there
+        let sender = source.inner.sender.clone();
+        let task: Task = Box::new(move |_| {
+            drop(source);
+            async {}.boxed_local()
+        });
+        assert!(sender.send(Command::Task(task)).is_ok());
+
+        // The thread stops on its own, releasing the file's lock
+        let options = SystemFileOptions::default().lock_timeout(Duration::from_secs(10));
+        let source = open(&path, options, FileSourceOptions::default());
+        assert_eq!(source.list_blocking().unwrap(), Vec::new());
+    }
+}
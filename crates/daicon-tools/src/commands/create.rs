@@ -1,9 +1,12 @@
-use anyhow::Error;
+use anyhow::{Context as _, Error};
 use clap::Args;
-use daicon::{open_file_source, FileSourceOptions};
-use daicon_native::open_system_file;
-use stewart::{Actor, Context, Id, World};
+use daicon::{open_file_source, protocol::source, FileSourceOptions};
+use daicon_native::{open_system_file, SystemFileOptions};
+use stewart::{Actor, Context, Handler, Id, World};
 use tracing::{event, instrument, Level};
+use uuid::Uuid;
+
+use crate::LOCK_TIMEOUT;
 
 /// Create a new daicon file.
 #[derive(Args, Debug)]
@@ -14,29 +17,73 @@ pub struct Command {
 }
 
 #[instrument("daicon-tools::start_create", skip_all)]
-pub fn start(world: &mut World, command: Command) -> Result<(), Error> {
+pub fn start(
+    world: &mut World,
+    command: Command,
+    on_result: Handler<Result<(), Error>>,
+) -> Result<(), Error> {
     event!(Level::INFO, "creating package");
 
     let id = world.create(Id::none(), "command-create")?;
+    let handler = Handler::to(id);
 
     // Open the target file
-    let file = open_system_file(world, id, command.target.clone(), true)?;
-    let _source = open_file_source(world, id, file, FileSourceOptions::default())?;
+    let file = open_system_file(
+        world,
+        id,
+        command.target.clone(),
+        SystemFileOptions::default()
+            .truncate(true)
+            .lock(true)
+            .lock_timeout(LOCK_TIMEOUT),
+    )?;
+    let source = open_file_source(world, id, file, FileSourceOptions::default())?;
+
+    // The source answers lists only after pending writes complete, so once it answers this its
+    // new table has been written, or it reports why that failed
+    let action = source::ListAction {
+        on_result: handler.map(Message::Result),
+    };
+    let message = source::Request {
+        id: Uuid::new_v4(),
+        action: source::Action::List(action),
+    };
+    source.handle(world, message);
 
     // Start the command actor
-    let actor = CreateCommandService {};
+    let actor = CreateCommandService { on_result };
     world.start(id, actor)?;
 
     Ok(())
 }
 
-struct CreateCommandService {}
+struct CreateCommandService {
+    on_result: Handler<Result<(), Error>>,
+}
 
 impl Actor for CreateCommandService {
-    type Message = ();
+    type Message = Message;
 
     #[instrument("CreateCommandService", skip_all)]
-    fn process(&mut self, _world: &mut World, _cx: Context<Self>) -> Result<(), Error> {
+    fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+        while let Some(message) = cx.next() {
+            match message {
+                Message::Result(response) => {
+                    let result = response
+                        .result
+                        .map(|_| ())
+                        .context("failed to create package");
+                    self.on_result.handle(world, result);
+
+                    cx.stop();
+                }
+            }
+        }
+
         Ok(())
     }
 }
+
+enum Message {
+    Result(source::ListResponse),
+}
@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Error};
+use clap::Args;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    output::{format_id, print_json, Format},
+    package::read_package,
+};
+
+/// Compare the entries of two daicon files.
+///
+/// Exits with an error if any differences are found.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Path of the old file.
+    #[arg(value_name = "OLD")]
+    old: String,
+
+    /// Path of the new file.
+    #[arg(value_name = "NEW")]
+    new: String,
+
+    /// Include byte-level statistics for changed entries.
+    #[arg(short, long)]
+    stats: bool,
+
+    /// Format to print the differences in.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    let old: BTreeMap<_, _> = read_package(&command.old)?
+        .into_iter()
+        .map(|(id, data)| (id.0, data))
+        .collect();
+    let new: BTreeMap<_, _> = read_package(&command.new)?
+        .into_iter()
+        .map(|(id, data)| (id.0, data))
+        .collect();
+
+    let mut report = Report::default();
+
+    for (id, data) in &old {
+        let Some(new_data) = new.get(id) else {
+            report.removed.push(EntryInfo::new(*id, data));
+            continue;
+        };
+
+        let old_info = EntryInfo::new(*id, data);
+        let new_info = EntryInfo::new(*id, new_data);
+        if old_info.hash == new_info.hash {
+            report.unchanged += 1;
+            continue;
+        }
+
+        let kind = if data.len() != new_data.len() {
+            ChangeKind::Size
+        } else {
+            ChangeKind::Content
+        };
+        let stats = command.stats.then(|| ByteStats::new(data, new_data));
+
+        report.changed.push(Change {
+            id: old_info.id,
+            kind,
+            old_size: old_info.size,
+            new_size: new_info.size,
+            old_hash: old_info.hash,
+            new_hash: new_info.hash,
+            stats,
+        });
+    }
+
+    for (id, data) in &new {
+        if !old.contains_key(id) {
+            report.added.push(EntryInfo::new(*id, data));
+        }
+    }
+
+    match command.format {
+        Format::Text => print_text(&report),
+        Format::Json => print_json(&report)?,
+    }
+
+    let differences = report.added.len() + report.removed.len() + report.changed.len();
+    if differences != 0 {
+        bail!("found {} difference(s)", differences);
+    }
+
+    Ok(())
+}
+
+fn print_text(report: &Report) {
+    for entry in &report.added {
+        println!(
+            "+ {}  size {}  {}",
+            entry.id,
+            entry.size,
+            short(&entry.hash)
+        );
+    }
+
+    for entry in &report.removed {
+        println!(
+            "- {}  size {}  {}",
+            entry.id,
+            entry.size,
+            short(&entry.hash)
+        );
+    }
+
+    for change in &report.changed {
+        match change.kind {
+            ChangeKind::Size => println!(
+                "~ {}  size {} -> {}  {} -> {}",
+                change.id,
+                change.old_size,
+                change.new_size,
+                short(&change.old_hash),
+                short(&change.new_hash)
+            ),
+            ChangeKind::Content => println!(
+                "~ {}  content {} -> {}",
+                change.id,
+                short(&change.old_hash),
+                short(&change.new_hash)
+            ),
+        }
+
+        if let Some(stats) = &change.stats {
+            println!(
+                "    {} bytes differ, common prefix {} bytes, common suffix {} bytes",
+                stats.differing_bytes, stats.common_prefix, stats.common_suffix
+            );
+        }
+    }
+
+    println!(
+        "{} added, {} removed, {} changed, {} unchanged",
+        report.added.len(),
+        report.removed.len(),
+        report.changed.len(),
+        report.unchanged
+    );
+}
+
+/// Shorten a hash for display, the full hash is still available in JSON output.
+fn short(hash: &str) -> &str {
+    &hash[..12]
+}
+
+#[derive(Serialize, Default)]
+struct Report {
+    added: Vec<EntryInfo>,
+    removed: Vec<EntryInfo>,
+    changed: Vec<Change>,
+    unchanged: usize,
+}
+
+#[derive(Serialize)]
+struct EntryInfo {
+    id: String,
+    size: usize,
+    /// SHA-256 of the entry's data, in hexadecimal.
+    hash: String,
+}
+
+impl EntryInfo {
+    fn new(id: u32, data: &[u8]) -> Self {
+        let hash = Sha256::digest(data)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Self {
+            id: format_id(daicon_types::Id(id)),
+            size: data.len(),
+            hash,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Change {
+    id: String,
+    kind: ChangeKind,
+    old_size: usize,
+    new_size: usize,
+    old_hash: String,
+    new_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<ByteStats>,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ChangeKind {
+    /// The size changed, and with it the content.
+    Size,
+    /// The size is the same, but the content changed.
+    Content,
+}
+
+#[derive(Serialize)]
+struct ByteStats {
+    /// Bytes that differ at the same offset, plus the bytes only in the longer data.
+    differing_bytes: usize,
+    common_prefix: usize,
+    common_suffix: usize,
+}
+
+impl ByteStats {
+    fn new(old: &[u8], new: &[u8]) -> Self {
+        let overlap = old.len().min(new.len());
+        let differing_bytes = old.iter().zip(new).filter(|(old, new)| old != new).count()
+            + (old.len().max(new.len()) - overlap);
+
+        let common_prefix = old
+            .iter()
+            .zip(new)
+            .take_while(|(old, new)| old == new)
+            .count();
+
+        // Don't count bytes twice if the prefix and suffix meet
+        let common_suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(overlap - common_prefix)
+            .take_while(|(old, new)| old == new)
+            .count();
+
+        Self {
+            differing_bytes,
+            common_prefix,
+            common_suffix,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use daicon_types::Id;
+    use uuid::Uuid;
+
+    use super::{run, Command};
+    use crate::{output::Format, package::write_package};
+
+    /// Package in the temporary directory, removed again when dropped.
+    struct TempPackage(String);
+
+    impl TempPackage {
+        fn new(entries: &[(u32, &[u8])]) -> Self {
+            let path = std::env::temp_dir().join(format!("daicon-diff-{}", Uuid::new_v4()));
+            let path = path.to_str().unwrap().to_string();
+
+            let entries = entries
+                .iter()
+                .map(|(id, data)| (Id(*id), data.to_vec()))
+                .collect();
+            write_package(&path, entries).unwrap();
+
+            Self(path)
+        }
+    }
+
+    impl Drop for TempPackage {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn diff(old: &TempPackage, new: &TempPackage) -> Result<(), anyhow::Error> {
+        let command = Command {
+            old: old.0.clone(),
+            new: new.0.clone(),
+            stats: false,
+            format: Format::Text,
+        };
+        run(command)
+    }
+
+    #[test]
+    fn fails_on_differences() {
+        let old = TempPackage::new(&[(1, b"abcd"), (2, b"efgh")]);
+        let same = TempPackage::new(&[(2, b"efgh"), (1, b"abcd")]);
+        assert!(diff(&old, &same).is_ok());
+
+        let added = TempPackage::new(&[(1, b"abcd"), (2, b"efgh"), (3, b"ijkl")]);
+        let removed = TempPackage::new(&[(1, b"abcd")]);
+        let changed = TempPackage::new(&[(1, b"abcd"), (2, b"efgx")]);
+        for new in [&added, &removed, &changed] {
+            assert!(diff(&old, new).is_err());
+        }
+    }
+}
@@ -1,12 +1,12 @@
-use anyhow::Error;
+use anyhow::{Context as _, Error};
 use clap::Args;
 use daicon::{open_file_source, protocol::source, FileSourceOptions};
-use daicon_native::open_system_file;
+use daicon_native::{open_system_file, SystemFileOptions};
 use stewart::{Actor, Context, Handler, Id, World};
 use tracing::{event, instrument, Level};
 use uuid::Uuid;
 
-use crate::parse_hex;
+use crate::{output::format_id, parse_hex, write_output, LOCK_TIMEOUT};
 
 /// Get an entry from a daicon file.
 #[derive(Args, Debug)]
@@ -19,13 +19,17 @@ pub struct Command {
     #[arg(short = 'd', long, value_name = "ID")]
     id: String,
 
-    /// Path of the output file to write.
+    /// Path of the output file to write, or `-` for stdout.
     #[arg(short, long, value_name = "PATH")]
     output: String,
 }
 
 #[instrument("daicon-tools::start_get", skip_all)]
-pub fn start(world: &mut World, command: Command) -> Result<(), Error> {
+pub fn start(
+    world: &mut World,
+    command: Command,
+    on_result: Handler<Result<(), Error>>,
+) -> Result<(), Error> {
     event!(Level::INFO, "getting file from package");
 
     let asset_id = parse_hex(&command.id)?;
@@ -34,7 +38,15 @@ pub fn start(world: &mut World, command: Command) -> Result<(), Error> {
     let handler = Handler::to(id);
 
     // Open the target file
-    let file = open_system_file(world, id, command.target.clone(), false)?;
+    let file = open_system_file(
+        world,
+        id,
+        command.target.clone(),
+        SystemFileOptions::default()
+            .read_only(true)
+            .lock(true)
+            .lock_timeout(LOCK_TIMEOUT),
+    )?;
     let options = FileSourceOptions::default().open_table(0);
     let source = open_file_source(world, id, file, options)?;
 
@@ -49,7 +61,11 @@ pub fn start(world: &mut World, command: Command) -> Result<(), Error> {
     };
     source.handle(world, message);
 
-    let actor = GetCommandService { command };
+    let actor = GetCommandService {
+        command,
+        asset_id,
+        on_result,
+    };
     world.start(id, actor)?;
 
     Ok(())
@@ -57,17 +73,19 @@ pub fn start(world: &mut World, command: Command) -> Result<(), Error> {
 
 struct GetCommandService {
     command: Command,
+    asset_id: daicon_types::Id,
+    on_result: Handler<Result<(), Error>>,
 }
 
 impl Actor for GetCommandService {
     type Message = Message;
 
-    fn process(&mut self, _world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+    fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
         while let Some(message) = cx.next() {
             match message {
                 Message::Result(response) => {
-                    let data = response.result?;
-                    std::fs::write(&self.command.output, data)?;
+                    let result = self.on_get_result(response);
+                    self.on_result.handle(world, result);
 
                     // We're done
                     cx.stop();
@@ -79,6 +97,16 @@ impl Actor for GetCommandService {
     }
 }
 
+impl GetCommandService {
+    fn on_get_result(&self, response: source::GetResponse) -> Result<(), Error> {
+        let data = response
+            .result
+            .with_context(|| format!("failed to get {}", format_id(self.asset_id)))?;
+
+        write_output(&self.command.output, &data)
+    }
+}
+
 enum Message {
     Result(source::GetResponse),
 }
@@ -0,0 +1,102 @@
+use anyhow::Error;
+use clap::Args;
+use daicon_native::MappedFile;
+use serde::Serialize;
+
+use crate::{
+    output::{format_id, print_json, Format},
+    tables::{read_chain, TableInfo},
+    LOCK_TIMEOUT,
+};
+
+/// List the tables and entries in a daicon file.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Path of the target file.
+    #[arg(short, long, value_name = "PATH")]
+    target: String,
+
+    /// Format to print the listing in.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    // Hold a shared lock while mapped, so locking writers can't change the file under us
+    let file = MappedFile::open_locked(&command.target, Some(LOCK_TIMEOUT))?;
+    let tables = read_chain(&file, 0)?;
+
+    match command.format {
+        Format::Text => print_text(&tables),
+        Format::Json => {
+            let listing: Vec<_> = tables.iter().map(TableListing::new).collect();
+            print_json(&listing)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_text(tables: &[TableInfo]) {
+    for table in tables {
+        let next = table
+            .header
+            .next()
+            .map(|value| format!("{:#010x}", value))
+            .unwrap_or_else(|| "none".to_string());
+        println!(
+            "table {:#010x}: capacity {}, valid {}, next {}",
+            table.offset,
+            table.header.capacity(),
+            table.header.valid(),
+            next
+        );
+
+        for entry in &table.entries {
+            println!(
+                "  {}  offset {:#010x}  size {}",
+                format_id(entry.id()),
+                table.entry_offset(entry),
+                entry.size()
+            );
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TableListing {
+    offset: u64,
+    capacity: u16,
+    valid: u16,
+    next: Option<u64>,
+    entries: Vec<EntryListing>,
+}
+
+impl TableListing {
+    fn new(table: &TableInfo) -> Self {
+        let entries = table
+            .entries
+            .iter()
+            .map(|entry| EntryListing {
+                id: format_id(entry.id()),
+                offset: table.entry_offset(entry),
+                size: entry.size(),
+            })
+            .collect();
+
+        Self {
+            offset: table.offset,
+            capacity: table.header.capacity(),
+            valid: table.header.valid(),
+            next: table.header.next().map(|value| value.get()),
+            entries,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EntryListing {
+    id: String,
+    offset: u64,
+    size: u32,
+}
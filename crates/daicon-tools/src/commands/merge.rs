@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Error};
+use clap::{Args, ValueEnum};
+use daicon_types::Id;
+use tracing::{event, Level};
+
+use crate::{
+    output::format_id,
+    package::{read_package, write_package},
+};
+
+/// Merge the entries of several daicon files into one new file.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Paths of the files to merge, in order.
+    #[arg(value_name = "PATH", required = true)]
+    inputs: Vec<String>,
+
+    /// Path of the output file to write.
+    #[arg(short, long, value_name = "PATH")]
+    output: String,
+
+    /// What to do if multiple files contain the same ID.
+    #[arg(long, value_enum, default_value_t = Conflict::Error)]
+    on_conflict: Conflict,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Conflict {
+    /// Fail without writing the output.
+    Error,
+    /// Keep the entry from the first file containing the ID.
+    First,
+    /// Keep the entry from the last file containing the ID.
+    Last,
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    event!(Level::INFO, "merging packages");
+
+    // Read everything before touching the output, which may also be one of the inputs
+    let mut entries: BTreeMap<u32, (usize, Vec<u8>)> = BTreeMap::new();
+    for (index, input) in command.inputs.iter().enumerate() {
+        for (id, data) in read_package(input)? {
+            let Some((previous, _)) = entries.get(&id.0) else {
+                entries.insert(id.0, (index, data));
+                continue;
+            };
+
+            let previous = &command.inputs[*previous];
+            match command.on_conflict {
+                Conflict::Error => bail!("{} is in both {} and {}", format_id(id), previous, input),
+                Conflict::First => {
+                    event!(
+                        Level::WARN,
+                        "keeping {} from {}, over {}",
+                        format_id(id),
+                        previous,
+                        input
+                    );
+                }
+                Conflict::Last => {
+                    event!(
+                        Level::WARN,
+                        "replacing {} from {}, with {}",
+                        format_id(id),
+                        previous,
+                        input
+                    );
+                    entries.insert(id.0, (index, data));
+                }
+            }
+        }
+    }
+
+    let count = entries.len();
+    let entries = entries
+        .into_iter()
+        .map(|(id, (_, data))| (Id(id), data))
+        .collect();
+    write_package(&command.output, entries)?;
+
+    println!(
+        "merged {} entries from {} files into {}",
+        count,
+        command.inputs.len(),
+        command.output
+    );
+
+    Ok(())
+}
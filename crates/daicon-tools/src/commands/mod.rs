@@ -1,3 +1,11 @@
 pub mod create;
+pub mod diff;
 pub mod get;
+pub mod list;
+pub mod merge;
+pub mod pack;
+pub mod repair;
+pub mod serve;
 pub mod set;
+pub mod unpack;
+pub mod validate;
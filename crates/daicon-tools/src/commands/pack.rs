@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context as _, Error};
+use clap::Args;
+use daicon_types::Id;
+use tracing::{event, Level};
+
+use crate::{
+    manifest::{hash_path, Manifest},
+    output::{format_id, print_mapping, Format},
+    package::write_package,
+};
+
+/// Pack the files in a directory into a new daicon file.
+///
+/// If a manifest is given, only the files it lists are packed, with the IDs it assigns.
+/// Without one, a `manifest.toml` at the root of the directory is used, as written by `unpack`,
+/// so unpacking and packing again keeps IDs the same.
+/// If there's no manifest at all, every file is packed with an ID derived from its relative path.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Path of the directory to pack.
+    #[arg(value_name = "DIR")]
+    directory: PathBuf,
+
+    /// Path of the target file.
+    #[arg(short, long, value_name = "PATH")]
+    target: String,
+
+    /// Path of a TOML or JSON manifest assigning IDs to files, `manifest.toml` in the directory
+    /// if it exists by default.
+    #[arg(short, long, value_name = "PATH")]
+    manifest: Option<PathBuf>,
+
+    /// Format to print the path to ID mapping in.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    event!(Level::INFO, "packing directory");
+
+    let default_manifest = command.directory.join("manifest.toml");
+    let manifest = match &command.manifest {
+        Some(path) => Some(path.as_path()),
+        None => Some(default_manifest.as_path()).filter(|path| path.is_file()),
+    };
+
+    let entries = match manifest {
+        Some(path) => {
+            event!(Level::DEBUG, path = %path.display(), "using manifest");
+            Manifest::read(path)?.parse_entries()?
+        }
+        None => hash_directory(&command.directory)?,
+    };
+
+    // Read everything before touching the target, so bad input doesn't clobber it
+    let mut data = Vec::new();
+    for (path, id) in &entries {
+        let bytes = std::fs::read(command.directory.join(path))
+            .with_context(|| format!("failed to read \"{}\"", path))?;
+        data.push((*id, bytes));
+    }
+
+    write_package(&command.target, data)?;
+
+    print_mapping(command.format, &entries)
+}
+
+/// Find all files in a directory, with IDs hashed from their relative paths.
+fn hash_directory(directory: &Path) -> Result<Vec<(String, Id)>, Error> {
+    let mut paths = Vec::new();
+    find_files(directory, "", &mut paths)?;
+    paths.retain(|path| path != "manifest.toml");
+    paths.sort();
+
+    let mut entries = Vec::new();
+    let mut used = HashMap::new();
+    for path in paths {
+        let id = hash_path(&path);
+
+        if let Some(other) = used.insert(id.0, path.clone()) {
+            bail!(
+                "\"{}\" and \"{}\" hash to the same ID {}, use a manifest to assign IDs",
+                other,
+                path,
+                format_id(id)
+            );
+        }
+
+        entries.push((path, id));
+    }
+
+    Ok(entries)
+}
+
+fn find_files(directory: &Path, prefix: &str, paths: &mut Vec<String>) -> Result<(), Error> {
+    let read_dir = std::fs::read_dir(directory)
+        .with_context(|| format!("failed to read directory {}", directory.display()))?;
+
+    for entry in read_dir {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            bail!("path {} is not valid UTF-8", entry.path().display());
+        };
+
+        // Always use `/`, so IDs don't depend on the platform
+        let path = format!("{}{}", prefix, name);
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            find_files(&entry.path(), &format!("{}/", path), paths)?;
+        } else if entry.path().is_file() {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use clap::Parser;
+    use uuid::Uuid;
+
+    use super::{run, Command};
+    use crate::{commands::unpack, output::Format, package::read_package};
+
+    /// Directory in the temporary directory, removed again when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("daicon-pack-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[derive(Parser)]
+    struct UnpackCli {
+        #[command(flatten)]
+        command: unpack::Command,
+    }
+
+    fn pack(directory: &Path, target: &str) {
+        let command = Command {
+            directory: directory.to_path_buf(),
+            target: target.to_string(),
+            manifest: None,
+            format: Format::Text,
+        };
+        run(command).unwrap();
+    }
+
+    #[test]
+    fn unpack_and_pack_keeps_ids() {
+        let temp = TempDir::new();
+        let source = temp.0.join("source");
+        std::fs::create_dir_all(source.join("nested")).unwrap();
+        std::fs::write(source.join("a.txt"), b"first").unwrap();
+        std::fs::write(source.join("nested/b.txt"), b"second").unwrap();
+
+        let first = temp.0.join("first.daicon").to_str().unwrap().to_string();
+        pack(&source, &first);
+        let mut packed = read_package(&first).unwrap();
+
+        // Unpacked without a manifest, so the files are named by ID
+        let unpacked = temp.0.join("unpacked");
+        let args = ["unpack", unpacked.to_str().unwrap(), "--target", &first];
+        unpack::run(UnpackCli::parse_from(args).command).unwrap();
+
+        // Without -m, packing picks up the written manifest, rather than hashing the names
+        let second = temp.0.join("second.daicon").to_str().unwrap().to_string();
+        pack(&unpacked, &second);
+        assert!(unpacked.join("manifest.toml").is_file());
+        let mut repacked = read_package(&second).unwrap();
+
+        packed.sort_by_key(|(id, _)| id.0);
+        repacked.sort_by_key(|(id, _)| id.0);
+        assert_eq!(packed, repacked);
+    }
+}
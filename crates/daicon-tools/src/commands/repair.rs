@@ -0,0 +1,348 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use anyhow::{bail, Error};
+use clap::Args;
+use daicon_native::MappedFile;
+use daicon_types::{Id, SIGNATURE};
+use serde::Serialize;
+
+use crate::{
+    output::{format_id, print_json, Format},
+    package::write_package,
+    tables::read_table,
+    LOCK_TIMEOUT,
+};
+
+/// Recover entries from a damaged daicon file, into a new file.
+///
+/// Scans the entire file for tables, rather than following the chain from the start, so
+/// entries can be recovered even if the first table or a link in the chain is damaged.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Path of the damaged file.
+    #[arg(short, long, value_name = "PATH")]
+    target: String,
+
+    /// Path of the new file to write recovered entries to.
+    #[arg(short, long, value_name = "PATH")]
+    output: String,
+
+    /// Format to print the report in.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    let file = MappedFile::open_locked(&command.target, Some(LOCK_TIMEOUT))?;
+    let (report, entries) = scan(&file);
+
+    // Entries are copied out, so the map can be released before writing, even to the same path
+    drop(file);
+
+    match command.format {
+        Format::Text => print_text(&report),
+        Format::Json => print_json(&report)?,
+    }
+
+    if report.tables.is_empty() {
+        bail!("no tables found, nothing could be recovered");
+    }
+
+    write_package(&command.output, entries)?;
+
+    Ok(())
+}
+
+fn print_text(report: &Report) {
+    for table in &report.tables {
+        println!(
+            "found table at {:#010x}: capacity {}, valid {}",
+            table.offset, table.capacity, table.valid
+        );
+
+        if table.valid > table.capacity {
+            println!(
+                "  valid count exceeds capacity by {}, recovering from every used slot",
+                table.valid - table.capacity
+            );
+        }
+    }
+
+    for skipped in &report.skipped {
+        println!(
+            "skipped table at {:#010x}: {}",
+            skipped.offset, skipped.reason
+        );
+    }
+
+    for entry in &report.lost {
+        println!(
+            "lost {} at {:#010x} with size {}: {}",
+            entry.id, entry.offset, entry.size, entry.reason
+        );
+    }
+
+    println!(
+        "recovered {} entries, lost {}",
+        report.recovered.len(),
+        report.lost.len()
+    );
+}
+
+#[derive(Serialize, Default)]
+struct Report {
+    tables: Vec<FoundTable>,
+    skipped: Vec<SkippedTable>,
+    recovered: Vec<EntryInfo>,
+    lost: Vec<LostEntry>,
+}
+
+#[derive(Serialize)]
+struct FoundTable {
+    offset: u64,
+    capacity: u16,
+    valid: u16,
+}
+
+#[derive(Serialize)]
+struct SkippedTable {
+    offset: u64,
+    reason: SkipReason,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum SkipReason {
+    Truncated,
+    InsideEntry,
+    Implausible,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            SkipReason::Truncated => "truncated by end of file",
+            SkipReason::InsideEntry => "inside the data of a recovered entry",
+            SkipReason::Implausible => "no capacity, or most entries out of file bounds",
+        };
+        f.write_str(text)
+    }
+}
+
+#[derive(Serialize)]
+struct EntryInfo {
+    id: String,
+    offset: u64,
+    size: u32,
+}
+
+#[derive(Serialize)]
+struct LostEntry {
+    id: String,
+    offset: u64,
+    size: u32,
+    reason: LostReason,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum LostReason {
+    OutOfBounds,
+    ConflictingDuplicate,
+}
+
+impl Display for LostReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            LostReason::OutOfBounds => "data is out of file bounds",
+            LostReason::ConflictingDuplicate => "ID already recovered with different data",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Find all plausible tables in a file, and copy out the entries they refer to.
+///
+/// Tables are accepted in file order, and a signature inside the data of an already accepted
+/// entry is assumed to be part of that data, for example a package stored inside a package.
+/// Signatures can also show up in data no accepted table refers to, so a table without capacity,
+/// or with most of its entries out of file bounds, is assumed to be a coincidence.
+fn scan(file: &MappedFile) -> (Report, Vec<(Id, Vec<u8>)>) {
+    let mut report = Report::default();
+    let mut entries: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+    let mut regions: Vec<(u64, u64)> = Vec::new();
+
+    for offset in find_signatures(file) {
+        if regions
+            .iter()
+            .any(|(start, end)| offset >= *start && offset < *end)
+        {
+            report.skipped.push(SkippedTable {
+                offset,
+                reason: SkipReason::InsideEntry,
+            });
+            continue;
+        }
+
+        let Some(table) = read_table(file, offset) else {
+            report.skipped.push(SkippedTable {
+                offset,
+                reason: SkipReason::Truncated,
+            });
+            continue;
+        };
+
+        // Interrupted writes can leave a table with a bogus count, in which case every slot is
+        // read, and empty ones are left out
+        let over_full = table.header.valid() > table.header.capacity();
+        let used: Vec<_> = table
+            .entries
+            .iter()
+            .filter(|entry| {
+                !(over_full && entry.id().0 == 0 && entry.offset() == 0 && entry.size() == 0)
+            })
+            .collect();
+
+        let out_of_bounds = used
+            .iter()
+            .filter(|entry| {
+                file.get(table.entry_offset(entry), entry.size() as u64)
+                    .is_none()
+            })
+            .count();
+        if table.header.capacity() == 0 || out_of_bounds * 2 > used.len() {
+            report.skipped.push(SkippedTable {
+                offset,
+                reason: SkipReason::Implausible,
+            });
+            continue;
+        }
+
+        report.tables.push(FoundTable {
+            offset,
+            capacity: table.header.capacity(),
+            valid: table.header.valid(),
+        });
+
+        for entry in used {
+            let id = format_id(entry.id());
+            let entry_offset = table.entry_offset(entry);
+            let lost = |reason| LostEntry {
+                id: id.clone(),
+                offset: entry_offset,
+                size: entry.size(),
+                reason,
+            };
+
+            let Some(data) = file.get(entry_offset, entry.size() as u64) else {
+                report.lost.push(lost(LostReason::OutOfBounds));
+                continue;
+            };
+
+            // The same entry found twice is fine, different data for the same ID isn't
+            if let Some(existing) = entries.get(&entry.id().0) {
+                if existing.as_slice() != data {
+                    report.lost.push(lost(LostReason::ConflictingDuplicate));
+                }
+                continue;
+            }
+
+            entries.insert(entry.id().0, data.to_vec());
+            regions.push((entry_offset, entry_offset + entry.size() as u64));
+            report.recovered.push(EntryInfo {
+                id,
+                offset: entry_offset,
+                size: entry.size(),
+            });
+        }
+    }
+
+    let entries = entries
+        .into_iter()
+        .map(|(id, data)| (Id(id), data))
+        .collect();
+    (report, entries)
+}
+
+/// Find every offset the table signature appears at.
+fn find_signatures(file: &MappedFile) -> Vec<u64> {
+    let Some(data) = file.get(0, file.len()) else {
+        return Vec::new();
+    };
+    let signature = SIGNATURE.to_le_bytes();
+
+    data.windows(signature.len())
+        .enumerate()
+        .filter(|(_, window)| *window == signature)
+        .map(|(offset, _)| offset as u64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scan, SkipReason};
+    use crate::tables::{map_bytes, write_table};
+
+    #[test]
+    fn salvages_over_full_table() {
+        // Two of the four slots are used, but the count claims ten
+        let mut data = write_table(4, 72, 0, &[(1, 0, 4), (2, 4, 4)]);
+        data[6] = 10;
+        data.extend_from_slice(b"abcdefgh");
+
+        let (report, entries) = scan(&map_bytes(&data));
+
+        assert_eq!(report.tables.len(), 1);
+        assert!(report.skipped.is_empty());
+        assert!(report.lost.is_empty());
+        let entries: Vec<_> = entries.into_iter().map(|(id, data)| (id.0, data)).collect();
+        assert_eq!(entries, [(1, b"abcd".to_vec()), (2, b"efgh".to_vec())]);
+    }
+
+    #[test]
+    fn skips_table_inside_entry() {
+        let inner = write_table(1, 36, 0, &[]);
+        let mut data = write_table(1, 36, 0, &[(1, 0, inner.len() as u32)]);
+        data.extend_from_slice(&inner);
+
+        let (report, entries) = scan(&map_bytes(&data));
+
+        assert_eq!(report.tables.len(), 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn skips_implausible_tables() {
+        let mut data = write_table(1, 36, 0, &[(1, 0, 4)]);
+        data.extend_from_slice(b"abcd");
+
+        // Left over data no table refers to anymore, which happens to contain signatures
+        let stray_offset = data.len() as u64;
+        data.extend_from_slice(&write_table(2, 1 << 40, 0, &[(7, 0, 16), (8, 0, 16)]));
+        let empty_offset = data.len() as u64;
+        data.extend_from_slice(&write_table(0, 0, 0, &[]));
+
+        let (report, entries) = scan(&map_bytes(&data));
+
+        assert_eq!(report.tables.len(), 1);
+        let skipped: Vec<_> = report
+            .skipped
+            .iter()
+            .map(|skipped| (skipped.offset, skipped.reason))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                (stray_offset, SkipReason::Implausible),
+                (empty_offset, SkipReason::Implausible)
+            ]
+        );
+        assert!(report.lost.is_empty());
+        let entries: Vec<_> = entries.into_iter().map(|(id, data)| (id.0, data)).collect();
+        assert_eq!(entries, [(1, b"abcd".to_vec())]);
+    }
+}
@@ -0,0 +1,775 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context as _, Error};
+use clap::Args;
+use tracing::{event, Level};
+use uuid::Uuid;
+
+/// Maximum size of a request line or header line.
+const MAX_LINE: u64 = 8 * 1024;
+
+/// Maximum amount of headers in a request.
+const MAX_HEADERS: usize = 100;
+
+/// How long a connection can stay idle, or stall sending a request, before it's closed.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Serve a directory over HTTP, for developing against `daicon-web`.
+///
+/// Supports single and multiple byte ranges, `ETag` and `If-Range`, and CORS from any origin.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Path of the directory to serve.
+    #[arg(value_name = "DIR", default_value = ".")]
+    directory: PathBuf,
+
+    /// Address to listen on.
+    #[arg(short, long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+    address: String,
+
+    /// Emulate a server without multipart support, serving the entire file if multiple ranges
+    /// are requested.
+    #[arg(long)]
+    no_multipart: bool,
+}
+
+struct ServeOptions {
+    /// Canonical path of the served directory.
+    root: PathBuf,
+    multipart: bool,
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    let root = command
+        .directory
+        .canonicalize()
+        .with_context(|| format!("failed to open directory {}", command.directory.display()))?;
+
+    let listener = TcpListener::bind(&command.address)
+        .with_context(|| format!("failed to listen on {}", command.address))?;
+
+    println!(
+        "serving {} on http://{}",
+        command.directory.display(),
+        listener.local_addr()?
+    );
+
+    let options = Arc::new(ServeOptions {
+        root,
+        multipart: !command.no_multipart,
+    });
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                event!(Level::WARN, ?error, "failed to accept connection");
+                continue;
+            }
+        };
+
+        let options = options.clone();
+        thread::spawn(move || {
+            if let Err(error) = handle_connection(stream, &options) {
+                event!(Level::DEBUG, ?error, "connection closed with error");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+struct Request {
+    method: String,
+    target: String,
+    /// Headers, with lowercase names.
+    headers: Vec<(String, String)>,
+    keep_alive: bool,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn handle_connection(stream: TcpStream, options: &ServeOptions) -> Result<(), Error> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    loop {
+        let request = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => break,
+            Err(error) => {
+                let _ = write_status(&mut writer, 400, false, true);
+                let _ = writer.flush();
+                return Err(error);
+            }
+        };
+
+        let status = respond(&mut writer, &request, options)?;
+        writer.flush()?;
+
+        event!(
+            Level::INFO,
+            "{} {} {}",
+            request.method,
+            request.target,
+            status
+        );
+
+        if !request.keep_alive {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the next request, `None` if the connection was closed before it started.
+fn read_request(reader: &mut impl BufRead) -> Result<Option<Request>, Error> {
+    let Some(line) = read_line(reader)? else {
+        return Ok(None);
+    };
+
+    let mut parts = line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        bail!("invalid request line");
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?.context("connection closed in headers")?;
+        if line.is_empty() {
+            break;
+        }
+
+        if headers.len() >= MAX_HEADERS {
+            bail!("too many headers");
+        }
+
+        let (name, value) = line.split_once(':').context("invalid header")?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    let mut request = Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        headers,
+        keep_alive: false,
+    };
+
+    // HTTP/1.1 connections are persistent by default, HTTP/1.0 ones have to ask for it
+    let connection = request
+        .header("connection")
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    request.keep_alive = match version {
+        "HTTP/1.1" => connection != "close",
+        "HTTP/1.0" => connection == "keep-alive",
+        _ => bail!("unsupported HTTP version"),
+    };
+
+    // We don't use request bodies, but they have to be skipped to get to the next request
+    if let Some(length) = request.header("content-length") {
+        let length: u64 = length.parse().context("invalid content length")?;
+        io::copy(&mut reader.take(length), &mut io::sink())?;
+    }
+
+    Ok(Some(request))
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>, Error> {
+    let mut line = String::new();
+    match reader.take(MAX_LINE).read_line(&mut line) {
+        Ok(_) => {}
+        // Idle connections time out between requests, which is a normal close
+        Err(error) if is_timeout(&error) && line.is_empty() => return Ok(None),
+        Err(error) => return Err(error.into()),
+    }
+
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let Some(line) = line.strip_suffix('\n') else {
+        bail!("line too long or incomplete");
+    };
+
+    Ok(Some(line.strip_suffix('\r').unwrap_or(line).to_string()))
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// Write the response to a request, returning the status code.
+fn respond(
+    writer: &mut impl Write,
+    request: &Request,
+    options: &ServeOptions,
+) -> Result<u16, Error> {
+    let head_only = match request.method.as_str() {
+        "GET" => false,
+        "HEAD" => true,
+        "OPTIONS" => {
+            // CORS preflight, which browsers send when requesting with a `Range` header
+            let headers = [
+                (
+                    "Access-Control-Allow-Methods",
+                    "GET, HEAD, OPTIONS".to_string(),
+                ),
+                (
+                    "Access-Control-Allow-Headers",
+                    "Range, If-Range".to_string(),
+                ),
+                ("Access-Control-Max-Age", "86400".to_string()),
+                ("Content-Length", "0".to_string()),
+            ];
+            write_head(writer, 204, request.keep_alive, &headers)?;
+            return Ok(204);
+        }
+        _ => {
+            write_status(writer, 405, request.keep_alive, true)?;
+            return Ok(405);
+        }
+    };
+
+    let Some((path, mut file, length)) = open_target(&options.root, &request.target) else {
+        write_status(writer, 404, request.keep_alive, !head_only)?;
+        return Ok(404);
+    };
+
+    let modified = file
+        .metadata()?
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let etag = format!("\"{:x}-{:x}\"", length, modified.as_nanos());
+    let content_type = content_type(&path);
+
+    let mut headers = vec![
+        ("Accept-Ranges", "bytes".to_string()),
+        ("ETag", etag.clone()),
+    ];
+
+    // Ranges only apply if the client's version of the file is still current
+    let if_range_matches = request.header("if-range").is_none_or(|value| value == etag);
+    let ranges = request
+        .header("range")
+        .filter(|_| if_range_matches)
+        .and_then(|value| parse_ranges(value, length));
+
+    let status = match ranges.as_deref() {
+        // Nothing requested can be served
+        Some([]) => {
+            headers.push(("Content-Range", format!("bytes */{}", length)));
+            headers.push(("Content-Length", "0".to_string()));
+            write_head(writer, 416, request.keep_alive, &headers)?;
+            416
+        }
+        Some(&[(start, end)]) => {
+            headers.push(("Content-Type", content_type.to_string()));
+            headers.push((
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, end, length),
+            ));
+            headers.push(("Content-Length", (end - start + 1).to_string()));
+            write_head(writer, 206, request.keep_alive, &headers)?;
+
+            if !head_only {
+                copy_range(&mut file, writer, start, end)?;
+            }
+            206
+        }
+        Some(ranges) if options.multipart => {
+            let boundary = Uuid::new_v4().simple().to_string();
+            let parts: Vec<_> = ranges
+                .iter()
+                .map(|(start, end)| {
+                    let head = format!(
+                        "--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        boundary, content_type, start, end, length
+                    );
+                    (head, *start, *end)
+                })
+                .collect();
+            let tail = format!("--{}--\r\n", boundary);
+
+            // Every part ends with a line break after its data
+            let content_length = parts
+                .iter()
+                .map(|(head, start, end)| head.len() as u64 + (end - start + 1) + 2)
+                .sum::<u64>()
+                + tail.len() as u64;
+
+            headers.push((
+                "Content-Type",
+                format!("multipart/byteranges; boundary={}", boundary),
+            ));
+            headers.push(("Content-Length", content_length.to_string()));
+            write_head(writer, 206, request.keep_alive, &headers)?;
+
+            if !head_only {
+                for (head, start, end) in parts {
+                    writer.write_all(head.as_bytes())?;
+                    copy_range(&mut file, writer, start, end)?;
+                    writer.write_all(b"\r\n")?;
+                }
+                writer.write_all(tail.as_bytes())?;
+            }
+            206
+        }
+        // Either no ranges, or multiple ranges that we're pretending not to support
+        _ => {
+            headers.push(("Content-Type", content_type.to_string()));
+            headers.push(("Content-Length", length.to_string()));
+            write_head(writer, 200, request.keep_alive, &headers)?;
+
+            if !head_only && length > 0 {
+                copy_range(&mut file, writer, 0, length - 1)?;
+            }
+            200
+        }
+    };
+
+    Ok(status)
+}
+
+/// Open the file a request target refers to, `None` if it doesn't exist or isn't allowed.
+fn open_target(root: &Path, target: &str) -> Option<(PathBuf, File, u64)> {
+    let target = target.split(['?', '#']).next()?;
+    let target = percent_decode(target)?;
+
+    // Only allow plain names, so requests can't escape the root
+    let mut path = root.to_path_buf();
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            _ if segment.contains(['\\', ':']) => return None,
+            _ => path.push(segment),
+        }
+    }
+
+    if path.is_dir() {
+        path.push("index.html");
+    }
+
+    // Symlinks could still point outside the root, so check where the path really ends up
+    let canonical = path.canonicalize().ok()?;
+    if !canonical.starts_with(root) {
+        return None;
+    }
+
+    let file = File::open(&canonical).ok()?;
+    let metadata = file.metadata().ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+
+    Some((path, file, metadata.len()))
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Parse a `Range` header into inclusive byte ranges.
+///
+/// Returns `None` if the header is invalid and should be ignored, and an empty list if none of
+/// the ranges can be satisfied.
+fn parse_ranges(value: &str, length: u64) -> Option<Vec<(u64, u64)>> {
+    let specs = value.trim().strip_prefix("bytes=")?;
+    let mut ranges = Vec::new();
+
+    for spec in specs.split(',') {
+        let (start, end) = spec.trim().split_once('-')?;
+
+        let range = if start.is_empty() {
+            // Suffix range, the last N bytes of the file
+            let suffix: u64 = end.parse().ok()?;
+            (suffix > 0 && length > 0).then(|| (length.saturating_sub(suffix), length - 1))
+        } else {
+            let start: u64 = start.parse().ok()?;
+            let end = if end.is_empty() {
+                u64::MAX
+            } else {
+                let end: u64 = end.parse().ok()?;
+                if end < start {
+                    return None;
+                }
+                end
+            };
+
+            (start < length).then(|| (start, end.min(length - 1)))
+        };
+
+        ranges.extend(range);
+    }
+
+    Some(ranges)
+}
+
+fn copy_range(file: &mut File, writer: &mut impl Write, start: u64, end: u64) -> Result<(), Error> {
+    file.seek(SeekFrom::Start(start))?;
+    let copied = io::copy(&mut file.take(end - start + 1), writer)?;
+
+    // The file changed size after we sent the headers, nothing can be done but drop the connection
+    if copied != end - start + 1 {
+        bail!("file truncated while serving");
+    }
+
+    Ok(())
+}
+
+/// Write a response without content, other than the status reason as text.
+fn write_status(
+    writer: &mut impl Write,
+    status: u16,
+    keep_alive: bool,
+    with_body: bool,
+) -> Result<(), Error> {
+    let body = reason(status);
+    let headers = [
+        ("Content-Type", "text/plain".to_string()),
+        ("Content-Length", body.len().to_string()),
+    ];
+    write_head(writer, status, keep_alive, &headers)?;
+
+    if with_body {
+        writer.write_all(body.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn write_head(
+    writer: &mut impl Write,
+    status: u16,
+    keep_alive: bool,
+    headers: &[(&str, String)],
+) -> Result<(), Error> {
+    write!(writer, "HTTP/1.1 {} {}\r\n", status, reason(status))?;
+
+    // Allow fetching from pages on any origin, and reading the headers `daicon-web` relies on
+    write!(writer, "Access-Control-Allow-Origin: *\r\n")?;
+    write!(
+        writer,
+        "Access-Control-Expose-Headers: Accept-Ranges, Content-Length, Content-Range, ETag\r\n"
+    )?;
+
+    if !keep_alive {
+        write!(writer, "Connection: close\r\n")?;
+    }
+
+    for (name, value) in headers {
+        write!(writer, "{}: {}\r\n", name, value)?;
+    }
+    write!(writer, "\r\n")?;
+
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        206 => "Partial Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        416 => "Range Not Satisfiable",
+        _ => "Unknown",
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|value| value.to_str());
+
+    // Browsers need the right type for pages, scripts, and streaming wasm compilation
+    match extension {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") => "application/json",
+        Some("wasm") => "application/wasm",
+        Some("txt" | "md") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use uuid::Uuid;
+
+    use super::{open_target, parse_ranges, respond, Request, ServeOptions};
+
+    /// Directory in the temporary directory, removed again when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("daicon-serve-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn parse_ranges_cases() {
+        // Expected inclusive ranges, `None` if the header should be ignored
+        type Expected = Option<&'static [(u64, u64)]>;
+
+        let cases: &[(&str, Expected)] = &[
+            ("bytes=0-4", Some(&[(0, 4)])),
+            ("bytes=3-100", Some(&[(3, 9)])),
+            // Suffix ranges
+            ("bytes=-3", Some(&[(7, 9)])),
+            ("bytes=-20", Some(&[(0, 9)])),
+            ("bytes=-0", Some(&[])),
+            // Open-ended ranges
+            ("bytes=5-", Some(&[(5, 9)])),
+            ("bytes=10-", Some(&[])),
+            // Unsatisfiable ranges are left out, the rest is kept
+            ("bytes=10-20", Some(&[])),
+            ("bytes=0-1, 20-30", Some(&[(0, 1)])),
+            // Multiple and overlapping ranges are kept as requested
+            ("bytes=0-1, 5-6", Some(&[(0, 1), (5, 6)])),
+            ("bytes=0-5,3-8", Some(&[(0, 5), (3, 8)])),
+            // Malformed headers are ignored
+            ("bytes=5-3", None),
+            ("items=0-1", None),
+            ("bytes=a-b", None),
+            ("bytes=0", None),
+            ("bytes=", None),
+            ("bytes=--1", None),
+            ("bytes=0-1,", None),
+        ];
+
+        for (value, expected) in cases {
+            let ranges = parse_ranges(value, 10);
+            assert_eq!(ranges.as_deref(), *expected, "parsing \"{}\"", value);
+        }
+
+        // Nothing in an empty file can be satisfied
+        assert_eq!(parse_ranges("bytes=-5", 0).as_deref(), Some(&[][..]));
+        assert_eq!(parse_ranges("bytes=0-", 0).as_deref(), Some(&[][..]));
+    }
+
+    struct Response {
+        status: u16,
+        head: String,
+        body: Vec<u8>,
+    }
+
+    impl Response {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.head.lines().find_map(|line| {
+                let (key, value) = line.split_once(": ")?;
+                key.eq_ignore_ascii_case(name).then_some(value)
+            })
+        }
+    }
+
+    /// Respond to a GET of `data.bin`, containing the bytes `0123456789`.
+    fn get(multipart: bool, headers: &[(&str, &str)]) -> Response {
+        let root = TempDir::new();
+        std::fs::write(root.0.join("data.bin"), b"0123456789").unwrap();
+        let options = ServeOptions {
+            root: root.0.canonicalize().unwrap(),
+            multipart,
+        };
+
+        let mut etag_request = request(Vec::new());
+        etag_request.method = "HEAD".to_string();
+        let mut output = Vec::new();
+        respond(&mut output, &etag_request, &options).unwrap();
+        let etag = parse(output).header("etag").unwrap().to_string();
+
+        // Headers can refer to the current ETag
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.replace("{etag}", &etag)))
+            .collect();
+
+        let mut output = Vec::new();
+        respond(&mut output, &request(headers), &options).unwrap();
+        parse(output)
+    }
+
+    fn request(headers: Vec<(String, String)>) -> Request {
+        Request {
+            method: "GET".to_string(),
+            target: "/data.bin".to_string(),
+            headers,
+            keep_alive: true,
+        }
+    }
+
+    fn parse(output: Vec<u8>) -> Response {
+        let split = output
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap();
+        let head = String::from_utf8(output[..split].to_vec()).unwrap();
+        let status = head[9..12].parse().unwrap();
+
+        Response {
+            status,
+            head,
+            body: output[split + 4..].to_vec(),
+        }
+    }
+
+    #[test]
+    fn respond_without_range() {
+        let response = get(true, &[]);
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"0123456789");
+    }
+
+    #[test]
+    fn respond_single_range() {
+        let response = get(true, &[("range", "bytes=-3")]);
+
+        assert_eq!(response.status, 206);
+        assert_eq!(response.header("content-range"), Some("bytes 7-9/10"));
+        assert_eq!(response.body, b"789");
+    }
+
+    #[test]
+    fn respond_unsatisfiable_range() {
+        let response = get(true, &[("range", "bytes=20-")]);
+
+        assert_eq!(response.status, 416);
+        assert_eq!(response.header("content-range"), Some("bytes */10"));
+        assert_eq!(response.body, b"");
+    }
+
+    #[test]
+    fn respond_malformed_range() {
+        let response = get(true, &[("range", "bytes=5-3")]);
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"0123456789");
+    }
+
+    #[test]
+    fn respond_multipart() {
+        let response = get(true, &[("range", "bytes=0-1,5-")]);
+
+        assert_eq!(response.status, 206);
+        let content_type = response.header("content-type").unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+
+        let expected = format!(
+            "--{b}\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+             --{b}\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 5-9/10\r\n\r\n56789\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(String::from_utf8(response.body.clone()).unwrap(), expected);
+        assert_eq!(
+            response.header("content-length"),
+            Some(expected.len().to_string().as_str())
+        );
+    }
+
+    #[test]
+    fn respond_no_multipart_serves_everything() {
+        let response = get(false, &[("range", "bytes=0-1,5-")]);
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"0123456789");
+
+        // A single range is still fine
+        let response = get(false, &[("range", "bytes=5-")]);
+        assert_eq!(response.status, 206);
+        assert_eq!(response.body, b"56789");
+    }
+
+    #[test]
+    fn respond_if_range() {
+        let matching = [("range", "bytes=5-"), ("if-range", "{etag}")];
+        let response = get(true, &matching);
+        assert_eq!(response.status, 206);
+        assert_eq!(response.body, b"56789");
+
+        // The client's version is outdated, so it gets the entire current file
+        let outdated = [("range", "bytes=5-"), ("if-range", "\"outdated\"")];
+        let response = get(true, &outdated);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"0123456789");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_target_stays_in_root() {
+        use std::os::unix::fs::symlink;
+
+        let base = TempDir::new();
+        let root = base.0.join("root");
+        std::fs::create_dir_all(root.join("dir")).unwrap();
+        std::fs::write(root.join("dir/file.txt"), b"inside").unwrap();
+        std::fs::write(base.0.join("secret.txt"), b"outside").unwrap();
+        symlink("../secret.txt", root.join("escape.txt")).unwrap();
+        symlink("dir/file.txt", root.join("link.txt")).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let found = |target| open_target(&root, target).map(|(_, _, length)| length);
+        assert_eq!(found("/dir/file.txt"), Some(6));
+        assert_eq!(found("/dir%2Ffile.txt"), Some(6));
+        assert_eq!(found("/link.txt"), Some(6));
+        assert_eq!(found("/escape.txt"), None);
+        assert_eq!(found("/../secret.txt"), None);
+        assert_eq!(found("/dir/missing.txt"), None);
+    }
+}
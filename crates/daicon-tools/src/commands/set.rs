@@ -1,12 +1,12 @@
-use anyhow::Error;
+use anyhow::{Context as _, Error};
 use clap::Args;
 use daicon::{open_file_source, protocol::source, FileSourceOptions};
-use daicon_native::open_system_file;
+use daicon_native::{open_system_file, SystemFileOptions};
 use stewart::{Actor, Context, Handler, Id, World};
 use tracing::{event, instrument, Level};
 use uuid::Uuid;
 
-use crate::parse_hex;
+use crate::{output::format_id, parse_hex, read_input, LOCK_TIMEOUT};
 
 /// Set or add an entry in a daicon file.
 #[derive(Args, Debug)]
@@ -19,27 +19,41 @@ pub struct Command {
     #[arg(short = 'd', long, value_name = "ID")]
     id: String,
 
-    /// Path of the input file to read.
+    /// Path of the input file to read, or `-` for stdin.
     #[arg(short, long, value_name = "PATH")]
     input: String,
 }
 
 #[instrument("daicon-tools::start_set", skip_all)]
-pub fn start(world: &mut World, command: Command) -> Result<(), Error> {
+pub fn start(
+    world: &mut World,
+    command: Command,
+    on_result: Handler<Result<(), Error>>,
+) -> Result<(), Error> {
     event!(Level::INFO, "setting file in package");
 
     let asset_id = parse_hex(&command.id)?;
 
+    // Read the input before opening, so the package isn't locked while waiting on a pipe
+    let data = read_input(&command.input)?;
+
     let id = world.create(Id::none(), "command-set")?;
     let handler = Handler::to(id);
 
     // Open the target file
-    let file = open_system_file(world, id, command.target.clone(), false)?;
+    let file = open_system_file(
+        world,
+        id,
+        command.target.clone(),
+        SystemFileOptions::default()
+            .must_exist(true)
+            .lock(true)
+            .lock_timeout(LOCK_TIMEOUT),
+    )?;
     let options = FileSourceOptions::default().open_table(0);
     let source = open_file_source(world, id, file, options)?;
 
     // Add the data to the source
-    let data = std::fs::read(&command.input)?;
     let action = source::SetAction {
         id: asset_id,
         data,
@@ -51,22 +65,33 @@ pub fn start(world: &mut World, command: Command) -> Result<(), Error> {
     };
     source.handle(world, message);
 
-    let actor = SetCommandService {};
+    let actor = SetCommandService {
+        asset_id,
+        on_result,
+    };
     world.start(id, actor)?;
 
     Ok(())
 }
 
-struct SetCommandService {}
+struct SetCommandService {
+    asset_id: daicon_types::Id,
+    on_result: Handler<Result<(), Error>>,
+}
 
 impl Actor for SetCommandService {
     type Message = Message;
 
     #[instrument("SetCommandService", skip_all)]
-    fn process(&mut self, _world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+    fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
         while let Some(message) = cx.next() {
             match message {
-                Message::Result(_) => {
+                Message::Result(response) => {
+                    let result = response
+                        .result
+                        .with_context(|| format!("failed to set {}", format_id(self.asset_id)));
+                    self.on_result.handle(world, result);
+
                     cx.stop();
                 }
             }
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{bail, Context as _, Error};
+use clap::Args;
+use daicon::FileSourceOptions;
+use daicon_native::{open_threaded_source, SystemFileOptions};
+use tracing::{event, Level};
+
+use crate::{
+    manifest::Manifest,
+    output::{format_id, print_mapping, Format},
+    LOCK_TIMEOUT,
+};
+
+/// Extract every entry in a daicon file into a directory.
+///
+/// Entries are named by their ID, or by path if a manifest names them.
+/// A manifest of everything extracted is written, which `pack` can use to pack the directory
+/// again with the same IDs.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Path of the directory to extract into.
+    #[arg(value_name = "DIR")]
+    directory: PathBuf,
+
+    /// Path of the target file.
+    #[arg(short, long, value_name = "PATH")]
+    target: String,
+
+    /// Path of a TOML or JSON manifest naming entries.
+    #[arg(short, long, value_name = "PATH")]
+    manifest: Option<PathBuf>,
+
+    /// Path to write the manifest of extracted entries to, `manifest.toml` in the directory by
+    /// default.
+    #[arg(long, value_name = "PATH")]
+    output_manifest: Option<PathBuf>,
+
+    /// Format to print the path to ID mapping in.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    event!(Level::INFO, "unpacking package");
+
+    let mut names = HashMap::new();
+    if let Some(path) = &command.manifest {
+        for (path, id) in Manifest::read(path)?.parse_entries()? {
+            names.insert(id.0, path);
+        }
+    }
+
+    let file_options = SystemFileOptions::default()
+        .read_only(true)
+        .lock(true)
+        .lock_timeout(LOCK_TIMEOUT);
+    let options = FileSourceOptions::default().open_table(0);
+    let source = open_threaded_source(command.target.clone(), file_options, options)?;
+
+    let mut ids = source.list_blocking()?;
+    ids.sort_by_key(|id| id.0);
+
+    let output_manifest = command
+        .output_manifest
+        .clone()
+        .unwrap_or_else(|| command.directory.join("manifest.toml"));
+
+    let mut manifest = Manifest::default();
+    let mut entries = Vec::new();
+    for id in ids {
+        let path = names
+            .remove(&id.0)
+            .unwrap_or_else(|| format!("{}.bin", format_id(id)));
+        let output = command.directory.join(&path);
+
+        if output == output_manifest {
+            bail!("entry {} would overwrite the manifest", format_id(id));
+        }
+
+        let data = source.get_blocking(id)?;
+        if let Some(parent) = output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&output, data)
+            .with_context(|| format!("failed to write {}", output.display()))?;
+
+        manifest.entries.insert(path.clone(), format_id(id));
+        entries.push((path, id));
+    }
+
+    for (id, path) in names {
+        event!(
+            Level::WARN,
+            "manifest entry \"{}\" ({}) not found in package",
+            path,
+            format_id(daicon_types::Id(id))
+        );
+    }
+
+    manifest.write(&output_manifest)?;
+
+    print_mapping(command.format, &entries)
+}
@@ -0,0 +1,439 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    mem::size_of,
+};
+
+use anyhow::{bail, Error};
+use clap::Args;
+use daicon_native::MappedFile;
+use daicon_types::{Header, Index};
+use serde::Serialize;
+
+use crate::{
+    output::{format_id, print_json, Format},
+    tables::read_table,
+    LOCK_TIMEOUT,
+};
+
+/// Check the integrity of a daicon file.
+///
+/// Exits with an error if any problems are found.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Path of the target file.
+    #[arg(short, long, value_name = "PATH")]
+    target: String,
+
+    /// Format to print the report in.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    // Hold a shared lock while mapped, so locking writers can't change the file under us
+    let file = MappedFile::open_locked(&command.target, Some(LOCK_TIMEOUT))?;
+    let report = validate(&file);
+
+    match command.format {
+        Format::Text => print_text(&report),
+        Format::Json => print_json(&report)?,
+    }
+
+    if !report.problems.is_empty() {
+        bail!("validation found {} problem(s)", report.problems.len());
+    }
+
+    Ok(())
+}
+
+fn print_text(report: &Report) {
+    println!(
+        "checked {} table(s) with {} entries",
+        report.tables, report.entries
+    );
+
+    if report.problems.is_empty() {
+        println!("no problems found");
+        return;
+    }
+
+    for problem in &report.problems {
+        println!("problem: {}", problem);
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    tables: usize,
+    entries: usize,
+    problems: Vec<Problem>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Problem {
+    InvalidSignature {
+        table: u64,
+    },
+    TruncatedTable {
+        table: u64,
+    },
+    ValidExceedsCapacity {
+        table: u64,
+        valid: u16,
+        capacity: u16,
+    },
+    ChainLoop {
+        table: u64,
+        next: u64,
+    },
+    EntryOutOfBounds {
+        id: String,
+        offset: u64,
+        size: u32,
+    },
+    Overlap {
+        first: Region,
+        second: Region,
+    },
+    DuplicateId {
+        id: String,
+        tables: Vec<u64>,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::InvalidSignature { table } => {
+                write!(f, "invalid signature on table at {:#010x}", table)
+            }
+            Problem::TruncatedTable { table } => {
+                write!(f, "table at {:#010x} truncated by end of file", table)
+            }
+            Problem::ValidExceedsCapacity {
+                table,
+                valid,
+                capacity,
+            } => write!(
+                f,
+                "table at {:#010x} has {} valid entries, but a capacity of {}",
+                table, valid, capacity
+            ),
+            Problem::ChainLoop { table, next } => write!(
+                f,
+                "table at {:#010x} loops back to table at {:#010x}",
+                table, next
+            ),
+            Problem::EntryOutOfBounds { id, offset, size } => write!(
+                f,
+                "entry {} at {:#010x} with size {} is out of file bounds",
+                id, offset, size
+            ),
+            Problem::Overlap { first, second } => write!(f, "{} overlaps {}", first, second),
+            Problem::DuplicateId { id, tables } => {
+                write!(f, "entry {} appears multiple times, in tables", id)?;
+                for table in tables {
+                    write!(f, " {:#010x}", table)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Region of the file in use by a table or entry.
+#[derive(Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Region {
+    Table { offset: u64, size: u64 },
+    Entry { id: String, offset: u64, size: u64 },
+}
+
+impl Region {
+    fn range(&self) -> (u64, u64) {
+        let (offset, size) = match self {
+            Region::Table { offset, size } => (*offset, *size),
+            Region::Entry { offset, size, .. } => (*offset, *size),
+        };
+        (offset, offset.saturating_add(size))
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Region::Table { offset, .. } => write!(f, "table at {:#010x}", offset),
+            Region::Entry { id, offset, .. } => write!(f, "entry {} at {:#010x}", id, offset),
+        }
+    }
+}
+
+fn validate(file: &MappedFile) -> Report {
+    let mut report = Report {
+        tables: 0,
+        entries: 0,
+        problems: Vec::new(),
+    };
+    let mut regions = Vec::new();
+    let mut ids: HashMap<u32, Vec<u64>> = HashMap::new();
+
+    // Walk the chain, stopping at the first table we can't trust to point to the next one
+    let mut visited = HashSet::new();
+    let mut next = Some(0);
+    while let Some(offset) = next.take() {
+        let Some(table) = read_table(file, offset) else {
+            report
+                .problems
+                .push(Problem::TruncatedTable { table: offset });
+            break;
+        };
+
+        if !table.header.is_valid() {
+            report
+                .problems
+                .push(Problem::InvalidSignature { table: offset });
+            break;
+        }
+
+        visited.insert(offset);
+        report.tables += 1;
+        report.entries += table.entries.len();
+
+        let header = &table.header;
+        if header.valid() > header.capacity() {
+            report.problems.push(Problem::ValidExceedsCapacity {
+                table: offset,
+                valid: header.valid(),
+                capacity: header.capacity(),
+            });
+        }
+
+        // The full allocated capacity belongs to the table, not just the valid entries
+        let size =
+            size_of::<Header>() as u64 + header.capacity() as u64 * size_of::<Index>() as u64;
+        if offset.saturating_add(size) > file.len() {
+            report
+                .problems
+                .push(Problem::TruncatedTable { table: offset });
+        }
+        regions.push(Region::Table { offset, size });
+
+        for entry in &table.entries {
+            let id = format_id(entry.id());
+            let entry_offset = table.entry_offset(entry);
+
+            if entry_offset.saturating_add(entry.size() as u64) > file.len() {
+                report.problems.push(Problem::EntryOutOfBounds {
+                    id: id.clone(),
+                    offset: entry_offset,
+                    size: entry.size(),
+                });
+            }
+
+            ids.entry(entry.id().0).or_default().push(offset);
+            regions.push(Region::Entry {
+                id,
+                offset: entry_offset,
+                size: entry.size() as u64,
+            });
+        }
+
+        if let Some(value) = header.next() {
+            if visited.contains(&value.get()) {
+                report.problems.push(Problem::ChainLoop {
+                    table: offset,
+                    next: value.get(),
+                });
+            } else {
+                next = Some(value.get());
+            }
+        }
+    }
+
+    check_overlaps(&mut report, regions);
+
+    let mut duplicates: Vec<_> = ids
+        .into_iter()
+        .filter(|(_, tables)| tables.len() > 1)
+        .collect();
+    duplicates.sort_by_key(|(id, _)| *id);
+    for (id, tables) in duplicates {
+        report.problems.push(Problem::DuplicateId {
+            id: format_id(daicon_types::Id(id)),
+            tables,
+        });
+    }
+
+    report
+}
+
+fn check_overlaps(report: &mut Report, mut regions: Vec<Region>) {
+    // Empty regions can't overlap anything
+    regions.retain(|region| {
+        let (start, end) = region.range();
+        start != end
+    });
+    regions.sort_by_key(|region| region.range());
+
+    // Compare against the region reaching furthest so far, which catches every overlapping region
+    // at least once without comparing every pair
+    let mut furthest: Option<&Region> = None;
+    for region in &regions {
+        let (start, end) = region.range();
+
+        if let Some(previous) = furthest {
+            let (_, previous_end) = previous.range();
+
+            if start < previous_end {
+                report.problems.push(Problem::Overlap {
+                    first: previous.clone(),
+                    second: region.clone(),
+                });
+            }
+
+            if end <= previous_end {
+                continue;
+            }
+        }
+
+        furthest = Some(region);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, Problem, Report};
+    use crate::tables::{map_bytes, write_table};
+
+    fn check(data: &[u8]) -> Report {
+        validate(&map_bytes(data))
+    }
+
+    #[test]
+    fn valid_package() {
+        let mut data = write_table(2, 48, 0, &[(1, 0, 4), (2, 4, 4)]);
+        data.extend_from_slice(b"abcdefgh");
+
+        let report = check(&data);
+
+        assert_eq!(report.tables, 1);
+        assert_eq!(report.entries, 2);
+        assert!(report.problems.is_empty());
+    }
+
+    #[test]
+    fn invalid_signature() {
+        let mut data = write_table(1, 36, 0, &[]);
+        data[0] = 0;
+
+        let report = check(&data);
+
+        assert!(matches!(
+            report.problems[..],
+            [Problem::InvalidSignature { table: 0 }]
+        ));
+    }
+
+    #[test]
+    fn truncated_table() {
+        let data = write_table(4, 72, 0, &[]);
+
+        let report = check(&data[..40]);
+
+        assert!(matches!(
+            report.problems[..],
+            [Problem::TruncatedTable { table: 0 }]
+        ));
+    }
+
+    #[test]
+    fn valid_exceeds_capacity() {
+        let mut data = write_table(1, 36, 0, &[(1, 0, 4)]);
+        data[6] = 5;
+        data.extend_from_slice(b"abcd");
+
+        let report = check(&data);
+
+        assert!(matches!(
+            report.problems[..],
+            [Problem::ValidExceedsCapacity {
+                table: 0,
+                valid: 5,
+                capacity: 1
+            }]
+        ));
+    }
+
+    #[test]
+    fn chain_loop() {
+        // The second table points back to itself
+        let mut data = write_table(1, 36, 36, &[]);
+        data.extend(write_table(1, 72, 36, &[]));
+
+        let report = check(&data);
+
+        assert_eq!(report.tables, 2);
+        assert!(matches!(
+            report.problems[..],
+            [Problem::ChainLoop {
+                table: 36,
+                next: 36
+            }]
+        ));
+    }
+
+    #[test]
+    fn entry_out_of_bounds() {
+        let mut data = write_table(1, 36, 0, &[(1, 0, 8)]);
+        data.extend_from_slice(b"abcd");
+
+        let report = check(&data);
+
+        let [Problem::EntryOutOfBounds { id, offset, size }] = &report.problems[..] else {
+            panic!("unexpected problems");
+        };
+        assert_eq!(id, "0x00000001");
+        assert_eq!((*offset, *size), (36, 8));
+    }
+
+    #[test]
+    fn overlapping_entries() {
+        let mut data = write_table(2, 48, 0, &[(1, 0, 4), (2, 2, 4)]);
+        data.extend_from_slice(b"abcdef");
+
+        let report = check(&data);
+
+        let [Problem::Overlap { first, second }] = &report.problems[..] else {
+            panic!("unexpected problems");
+        };
+        assert_eq!(first.to_string(), "entry 0x00000001 at 0x00000030");
+        assert_eq!(second.to_string(), "entry 0x00000002 at 0x00000032");
+    }
+
+    #[test]
+    fn entry_overlapping_table() {
+        let mut data = write_table(1, 0, 0, &[(1, 0, 4)]);
+        data.extend_from_slice(b"abcd");
+
+        let report = check(&data);
+
+        assert!(matches!(report.problems[..], [Problem::Overlap { .. }]));
+    }
+
+    #[test]
+    fn duplicate_id() {
+        let mut data = write_table(1, 72, 36, &[(1, 0, 4)]);
+        data.extend(write_table(1, 72, 0, &[(1, 4, 4)]));
+        data.extend_from_slice(b"abcdefgh");
+
+        let report = check(&data);
+
+        let [Problem::DuplicateId { id, tables }] = &report.problems[..] else {
+            panic!("unexpected problems");
+        };
+        assert_eq!(id, "0x00000001");
+        assert_eq!(tables, &[0, 36]);
+    }
+}
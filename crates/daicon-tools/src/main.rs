@@ -1,20 +1,36 @@
 mod commands;
+mod manifest;
+mod output;
+mod package;
+mod tables;
 
-use anyhow::{bail, Error};
-use clap::{Parser, Subcommand};
+use std::{
+    cell::RefCell,
+    io::{Read, Write},
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{bail, Context as _, Error};
+use clap::{ArgAction, Parser, Subcommand};
 use daicon_types::Id;
-use stewart::World;
+use stewart::{Handler, World};
 use tracing::{event, Level};
 use tracing_subscriber::{prelude::*, EnvFilter, FmtSubscriber};
 
-use crate::commands::{create, get, set};
+use crate::commands::{create, diff, get, list, merge, pack, repair, serve, set, unpack, validate};
+
+/// How long commands wait for other processes to release a package they're working on.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
 
 fn main() {
     let args = CliArgs::parse();
 
-    let filter = EnvFilter::builder().parse("trace,stewart=info").unwrap();
+    // Log to stderr, so stdout is left free for command output
+    let filter = EnvFilter::builder().parse(args.log_filter()).unwrap();
     let subscriber = FmtSubscriber::builder()
         .with_max_level(Level::TRACE)
+        .with_writer(std::io::stderr)
         .without_time()
         .with_target(false)
         .finish()
@@ -35,18 +51,34 @@ fn try_main(args: CliArgs) -> Result<(), Error> {
     // Set up the runtime
     let mut world = World::default();
 
+    // Commands report back their final result
+    let result = Rc::new(RefCell::new(None));
+    let result_handler = result.clone();
+    let on_result = Handler::none().map(move |value: Result<(), Error>| {
+        *result_handler.borrow_mut() = Some(value);
+    });
+
     // Start the command actor
     match args.command {
-        Command::Create(command) => commands::create::start(&mut world, command)?,
-        Command::Set(command) => commands::set::start(&mut world, command)?,
-        Command::Get(command) => commands::get::start(&mut world, command)?,
+        Command::Create(command) => commands::create::start(&mut world, command, on_result)?,
+        Command::Set(command) => commands::set::start(&mut world, command, on_result)?,
+        Command::Get(command) => commands::get::start(&mut world, command, on_result)?,
+        Command::List(command) => return commands::list::run(command),
+        Command::Validate(command) => return commands::validate::run(command),
+        Command::Pack(command) => return commands::pack::run(command),
+        Command::Unpack(command) => return commands::unpack::run(command),
+        Command::Serve(command) => return commands::serve::run(command),
+        Command::Merge(command) => return commands::merge::run(command),
+        Command::Diff(command) => return commands::diff::run(command),
+        Command::Repair(command) => return commands::repair::run(command),
     };
 
     // Run the command until it's done
     world.run_until_idle()?;
 
-    // TODO: Receive command errors
-    Ok(())
+    // If an actor failed along the way, the command never gets to report back
+    let result = result.borrow_mut().take();
+    result.context("command stopped without reporting a result")?
 }
 
 /// Pterodactil CLI toolkit for working with dacti packages.
@@ -55,6 +87,29 @@ fn try_main(args: CliArgs) -> Result<(), Error> {
 struct CliArgs {
     #[command(subcommand)]
     command: Command,
+
+    /// Log more details, repeat for even more.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only log errors.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+impl CliArgs {
+    fn log_filter(&self) -> &'static str {
+        if self.quiet {
+            return "error";
+        }
+
+        match self.verbose {
+            0 => "warn",
+            1 => "info",
+            2 => "debug,stewart=info",
+            _ => "trace,stewart=info",
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -62,6 +117,14 @@ enum Command {
     Create(create::Command),
     Set(set::Command),
     Get(get::Command),
+    List(list::Command),
+    Validate(validate::Command),
+    Pack(pack::Command),
+    Unpack(unpack::Command),
+    Serve(serve::Command),
+    Merge(merge::Command),
+    Diff(diff::Command),
+    Repair(repair::Command),
 }
 
 fn parse_hex(str: &str) -> Result<Id, Error> {
@@ -73,3 +136,29 @@ fn parse_hex(str: &str) -> Result<Id, Error> {
 
     Ok(Id(result))
 }
+
+/// Read all data from a file, or from stdin if the path is `-`.
+fn read_input(path: &str) -> Result<Vec<u8>, Error> {
+    if path == "-" {
+        let mut data = Vec::new();
+        std::io::stdin()
+            .lock()
+            .read_to_end(&mut data)
+            .context("failed to read stdin")?;
+        return Ok(data);
+    }
+
+    std::fs::read(path).with_context(|| format!("failed to read {}", path))
+}
+
+/// Write all data to a file, or to stdout if the path is `-`.
+fn write_output(path: &str, data: &[u8]) -> Result<(), Error> {
+    if path == "-" {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(data).context("failed to write stdout")?;
+        stdout.flush().context("failed to write stdout")?;
+        return Ok(());
+    }
+
+    std::fs::write(path, data).with_context(|| format!("failed to write {}", path))
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
+use anyhow::{bail, Context as _, Error};
+use daicon_types::Id;
+use serde::{Deserialize, Serialize};
+
+use crate::{output::format_id, parse_hex};
+
+/// Mapping of file paths to entry IDs, used when packing and unpacking directories.
+///
+/// Paths are relative to the packed directory, using `/` as separator.
+/// IDs are hexadecimal strings, the same as commands take as input.
+///
+/// ```toml
+/// [entries]
+/// "textures/grass.png" = "0x1f063ad4"
+/// ```
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Manifest {
+    pub entries: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Read a manifest, as TOML or JSON depending on the file extension.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read manifest {}", path.display()))?;
+
+        let manifest = match ManifestFormat::from_path(path)? {
+            ManifestFormat::Toml => toml::from_str(&text)?,
+            ManifestFormat::Json => serde_json::from_str(&text)?,
+        };
+
+        Ok(manifest)
+    }
+
+    /// Write a manifest, as TOML or JSON depending on the file extension.
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let text = match ManifestFormat::from_path(path)? {
+            ManifestFormat::Toml => toml::to_string_pretty(self)?,
+            ManifestFormat::Json => serde_json::to_string_pretty(self)?,
+        };
+
+        std::fs::write(path, text)
+            .with_context(|| format!("failed to write manifest {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Parse the entries, failing if any ID is invalid or used more than once, or if any path
+    /// isn't relative.
+    pub fn parse_entries(&self) -> Result<Vec<(String, Id)>, Error> {
+        let mut entries = Vec::new();
+        let mut used = BTreeMap::new();
+
+        for (path, id) in &self.entries {
+            check_relative(path)?;
+            let id = parse_hex(id).with_context(|| format!("invalid ID for \"{}\"", path))?;
+
+            if let Some(other) = used.insert(id.0, path) {
+                bail!(
+                    "\"{}\" and \"{}\" both use ID {}",
+                    other,
+                    path,
+                    format_id(id)
+                );
+            }
+
+            entries.push((path.clone(), id));
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Make sure a manifest path stays inside the directory being packed or unpacked.
+fn check_relative(path: &str) -> Result<(), Error> {
+    let valid = Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+
+    if !valid {
+        bail!("manifest path \"{}\" must be relative, without `..`", path);
+    }
+
+    Ok(())
+}
+
+enum ManifestFormat {
+    Toml,
+    Json,
+}
+
+impl ManifestFormat {
+    fn from_path(path: &Path) -> Result<Self, Error> {
+        match path.extension().and_then(|value| value.to_str()) {
+            Some("toml") => Ok(Self::Toml),
+            Some("json") => Ok(Self::Json),
+            _ => bail!("manifest must have a .toml or .json extension"),
+        }
+    }
+}
+
+/// Derive an entry ID from a relative path, for files not listed in a manifest.
+///
+/// This uses 32-bit FNV-1a, which is stable between versions and platforms.
+pub fn hash_path(path: &str) -> Id {
+    let mut hash: u32 = 0x811c9dc5;
+
+    for byte in path.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+
+    Id(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash_path;
+
+    #[test]
+    fn hash_path_matches_fnv1a() {
+        assert_eq!(hash_path("").0, 0x811c9dc5);
+        assert_eq!(hash_path("a").0, 0xe40c292c);
+        assert_eq!(hash_path("foobar").0, 0xbf9cf968);
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::Error;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Format of command output.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Human readable text.
+    Text,
+    /// JSON, for consumption by scripts.
+    Json,
+}
+
+/// Print a value as pretty JSON.
+pub fn print_json(value: &impl Serialize) -> Result<(), Error> {
+    let json = serde_json::to_string_pretty(value)?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Format an ID the same way commands take it as input.
+pub fn format_id(id: daicon_types::Id) -> String {
+    format!("{:#010x}", id.0)
+}
+
+/// Print a mapping of paths to IDs, as used by `pack` and `unpack`.
+pub fn print_mapping(format: Format, entries: &[(String, daicon_types::Id)]) -> Result<(), Error> {
+    match format {
+        Format::Text => {
+            for (path, id) in entries {
+                println!("{} {}", format_id(*id), path);
+            }
+        }
+        Format::Json => {
+            let listing: Vec<_> = entries
+                .iter()
+                .map(|(path, id)| MappedEntry {
+                    path,
+                    id: format_id(*id),
+                })
+                .collect();
+            print_json(&listing)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct MappedEntry<'a> {
+    path: &'a str,
+    id: String,
+}
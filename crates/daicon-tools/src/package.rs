@@ -0,0 +1,49 @@
+use anyhow::{bail, Error};
+use daicon::FileSourceOptions;
+use daicon_native::{open_threaded_source, SystemFileOptions};
+use daicon_types::Id;
+use futures::{executor::block_on, future::try_join_all};
+
+use crate::LOCK_TIMEOUT;
+
+/// Read all entries of a daicon file.
+pub fn read_package(path: &str) -> Result<Vec<(Id, Vec<u8>)>, Error> {
+    let file_options = SystemFileOptions::default()
+        .read_only(true)
+        .lock(true)
+        .lock_timeout(LOCK_TIMEOUT);
+    let options = FileSourceOptions::default().open_table(0);
+    let source = open_threaded_source(path.to_string(), file_options, options)?;
+
+    let ids = source.list_blocking()?;
+    let pending = ids.iter().map(|id| source.get(*id));
+    let data = block_on(try_join_all(pending))?;
+
+    Ok(ids.into_iter().zip(data).collect())
+}
+
+/// Write a new daicon file with the given entries, replacing any existing file.
+pub fn write_package(target: &str, entries: Vec<(Id, Vec<u8>)>) -> Result<(), Error> {
+    // Size the table to fit everything, checked before the target gets truncated
+    if entries.len() > u16::MAX as usize {
+        bail!(
+            "can't write {} entries, a package holds at most {}",
+            entries.len(),
+            u16::MAX
+        );
+    }
+    let capacity = entries.len().max(1) as u16;
+
+    let file_options = SystemFileOptions::default()
+        .truncate(true)
+        .lock(true)
+        .lock_timeout(LOCK_TIMEOUT);
+    let options = FileSourceOptions::default().allocate_capacity(capacity);
+    let source = open_threaded_source(target.to_string(), file_options, options)?;
+
+    // Send all sets at once, so the source can batch them into a single table write
+    let pending = entries.into_iter().map(|(id, data)| source.set(id, data));
+    block_on(try_join_all(pending))?;
+
+    Ok(())
+}
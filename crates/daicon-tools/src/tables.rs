@@ -0,0 +1,111 @@
+use std::{collections::HashSet, mem::size_of};
+
+use anyhow::{bail, Context as _, Error};
+use bytemuck::pod_read_unaligned;
+use daicon_native::MappedFile;
+use daicon_types::{Header, Index};
+
+/// Table read from a daicon file.
+pub struct TableInfo {
+    /// Offset of the table itself in the file.
+    pub offset: u64,
+    pub header: Header,
+    /// Indices that contain valid data.
+    pub entries: Vec<Index>,
+}
+
+impl TableInfo {
+    /// Get the absolute offset in the file of an entry's data.
+    pub fn entry_offset(&self, entry: &Index) -> u64 {
+        self.header.offset().saturating_add(entry.offset() as u64)
+    }
+}
+
+/// Read the table at an offset, `None` if the table doesn't fit in the file.
+///
+/// Tables can be at any offset, so this doesn't require alignment.
+/// This does not check the signature.
+/// A table can't hold more than its capacity, so if the valid count is larger than that, only
+/// the capacity's worth of indices is read.
+pub fn read_table(file: &MappedFile, offset: u64) -> Option<TableInfo> {
+    let header_size = size_of::<Header>() as u64;
+    let header: Header = pod_read_unaligned(file.get(offset, header_size)?);
+
+    let count = header.valid().min(header.capacity());
+    let entries_size = count as u64 * size_of::<Index>() as u64;
+    let entries = file
+        .get(offset + header_size, entries_size)?
+        .chunks_exact(size_of::<Index>())
+        .map(pod_read_unaligned)
+        .collect();
+
+    let table = TableInfo {
+        offset,
+        header,
+        entries,
+    };
+    Some(table)
+}
+
+/// Read the chain of tables starting at `offset`.
+pub fn read_chain(file: &MappedFile, offset: u64) -> Result<Vec<TableInfo>, Error> {
+    let mut tables = Vec::new();
+    let mut visited = HashSet::new();
+    let mut next = Some(offset);
+
+    while let Some(offset) = next {
+        if !visited.insert(offset) {
+            bail!("table chain loops back to table at {:#010x}", offset);
+        }
+
+        let table = read_table(file, offset)
+            .with_context(|| format!("table at {:#010x} truncated by end of file", offset))?;
+
+        if !table.header.is_valid() {
+            bail!("invalid table signature at {:#010x}", offset);
+        }
+
+        next = table.header.next().map(|value| value.get());
+        tables.push(table);
+    }
+
+    Ok(tables)
+}
+
+/// Serialize a table, with entries given as `(id, relative offset, size)`.
+#[cfg(test)]
+pub fn write_table(capacity: u16, offset: u64, next: u64, entries: &[(u32, u32, u32)]) -> Vec<u8> {
+    use bytemuck::bytes_of;
+
+    let mut header = Header::default();
+    header.set_capacity(capacity);
+    header.set_valid(entries.len() as u16);
+    header.set_offset(offset);
+    header.set_next(std::num::NonZeroU64::new(next));
+
+    let mut data = bytes_of(&header).to_vec();
+    for (id, offset, size) in entries {
+        let mut index = Index::default();
+        index.set_id(daicon_types::Id(*id));
+        index.set_offset(*offset);
+        index.set_size(*size);
+        data.extend_from_slice(bytes_of(&index));
+    }
+    data.resize(
+        size_of::<Header>() + capacity as usize * size_of::<Index>(),
+        0,
+    );
+
+    data
+}
+
+/// Map data through a temporary file.
+#[cfg(test)]
+pub fn map_bytes(data: &[u8]) -> MappedFile {
+    let path = std::env::temp_dir().join(format!("daicon-tools-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&path, data).unwrap();
+    let file = MappedFile::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    file
+}
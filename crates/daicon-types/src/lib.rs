@@ -4,10 +4,12 @@
 
 mod header;
 mod index;
+mod table_ref;
 
 pub use self::{
     header::Header,
     index::{Id, Index},
+    table_ref::TableRef,
 };
 
 /// Magic signature of a daicon 0.x.x header, literally equivalent to 0xFF followed by ASCII "dc0".
@@ -0,0 +1,45 @@
+use std::mem::size_of;
+
+use bytemuck::{try_cast_slice, try_from_bytes};
+
+use crate::{Header, Id, Index};
+
+/// Borrowed view of a daicon table, parsed in-place without copying.
+#[derive(PartialEq, Hash, Debug, Clone, Copy)]
+pub struct TableRef<'a> {
+    header: &'a Header,
+    entries: &'a [Index],
+}
+
+impl<'a> TableRef<'a> {
+    /// Parse a table from the start of `data`.
+    ///
+    /// Returns `None` if `data` is too small to contain the header and valid indices, or if it
+    /// isn't aligned for `Header`.
+    /// This does not check the signature, use `Header::is_valid` for that.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let header_size = size_of::<Header>();
+        let header: &Header = try_from_bytes(data.get(..header_size)?).ok()?;
+
+        let entries_size = header.valid() as usize * size_of::<Index>();
+        let entries = data.get(header_size..header_size + entries_size)?;
+        let entries = try_cast_slice(entries).ok()?;
+
+        Some(Self { header, entries })
+    }
+
+    /// Get the header of the table.
+    pub fn header(&self) -> &'a Header {
+        self.header
+    }
+
+    /// Get the indices that contain valid data.
+    pub fn entries(&self) -> &'a [Index] {
+        self.entries
+    }
+
+    /// Find the index of an entry by ID.
+    pub fn find(&self, id: Id) -> Option<&'a Index> {
+        self.entries.iter().find(|entry| entry.id() == id)
+    }
+}
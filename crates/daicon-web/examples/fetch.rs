@@ -1,8 +1,6 @@
-use std::{cell::RefCell, rc::Rc};
-
 use anyhow::{Context as _, Error};
-use daicon::{open_file_source, protocol::source, FileSourceOptions};
-use daicon_web::open_fetch_file;
+use daicon::{open_file_source, protocol::source, Executor, FileSourceOptions};
+use daicon_web::{open_fetch_file, BrowserTransport, FetchFileOptions};
 use stewart::{Actor, Context, Handler, Id, World};
 use tracing::{event, Level};
 use uuid::Uuid;
@@ -13,21 +11,33 @@ fn main() {
     tracing_wasm::set_as_global_default();
 
     event!(Level::INFO, "initializing world...");
-    let world = World::default();
-    let hnd = Rc::new(RefCell::new(world));
+    let executor = Executor::default();
+    executor.with_world(|world| start(world, &executor));
 
-    let mut world = hnd.borrow_mut();
+    // Process everything, fetch completions will continue from the browser's event loop
+    executor.run_until_idle().unwrap();
+}
 
+fn start(world: &mut World, executor: &Executor) {
     let id = world.create(Id::none(), "fetch-example").unwrap();
     let handler = Handler::to(id);
 
     event!(Level::INFO, "initializing fetch service...");
     let url = "http://localhost:8080/package.example";
-    let file = open_fetch_file(&mut world, id, url.to_string(), hnd.clone()).unwrap();
+    let options = FetchFileOptions::default();
+    let file = open_fetch_file(
+        world,
+        id,
+        url.to_string(),
+        BrowserTransport::new(),
+        executor.clone(),
+        options,
+    )
+    .unwrap();
 
     event!(Level::INFO, "initializing daicon service...");
     let options = FileSourceOptions::default().open_table(0);
-    let source = open_file_source(&mut world, id, file, options).unwrap();
+    let source = open_file_source(world, id, file, options).unwrap();
 
     event!(Level::INFO, "starting example service...");
     world.start(id, ExampleService).unwrap();
@@ -41,7 +51,7 @@ fn main() {
         id: Uuid::new_v4(),
         action: source::Action::Get(action),
     };
-    source.handle(&mut world, message);
+    source.handle(world, message);
 
     let action = source::GetAction {
         id: source::Id(0x1f063ad4),
@@ -51,10 +61,7 @@ fn main() {
         id: Uuid::new_v4(),
         action: source::Action::Get(action),
     };
-    source.handle(&mut world, message);
-
-    // Process everything
-    world.run_until_idle().unwrap();
+    source.handle(world, message);
 }
 
 struct ExampleService;
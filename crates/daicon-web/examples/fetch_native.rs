@@ -0,0 +1,78 @@
+use anyhow::Error;
+use daicon::{open_file_source, protocol::source, Executor, FileSourceOptions};
+use daicon_web::{open_fetch_file, FetchFileOptions, NativeTransport};
+use futures::executor::LocalPool;
+use stewart::{Actor, Context, Handler, Id, World};
+use tracing::{event, Level};
+use uuid::Uuid;
+
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    event!(Level::INFO, "initializing world...");
+    let executor = Executor::default();
+    let mut pool = LocalPool::new();
+
+    executor.with_world(|world| {
+        let id = world.create(Id::none(), "fetch-example").unwrap();
+        let handler = Handler::to(id);
+
+        event!(Level::INFO, "initializing fetch service...");
+        let url = "http://localhost:8080/package.example";
+        let transport = NativeTransport::new(pool.spawner());
+        let options = FetchFileOptions::default();
+        let file = open_fetch_file(
+            world,
+            id,
+            url.to_string(),
+            transport,
+            executor.clone(),
+            options,
+        )
+        .unwrap();
+
+        event!(Level::INFO, "initializing daicon service...");
+        let options = FileSourceOptions::default().open_table(0);
+        let source = open_file_source(world, id, file, options).unwrap();
+
+        event!(Level::INFO, "starting example service...");
+        world.start(id, ExampleService).unwrap();
+
+        event!(Level::INFO, "dispatching requests...");
+        for asset_id in [0xbacc2ba1, 0x1f063ad4] {
+            let action = source::GetAction {
+                id: source::Id(asset_id),
+                on_result: handler.clone(),
+            };
+            let message = source::Request {
+                id: Uuid::new_v4(),
+                action: source::Action::Get(action),
+            };
+            source.handle(world, message);
+        }
+    });
+    executor.run_until_idle().unwrap();
+
+    // Process everything, until all fetches are done
+    pool.run();
+}
+
+struct ExampleService;
+
+impl Actor for ExampleService {
+    type Message = source::GetResponse;
+
+    fn process(&mut self, _world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+        while let Some(message) = cx.next() {
+            event!(Level::INFO, "received result");
+
+            // Decode the data as a text file
+            let data = message.result?;
+            let text = std::str::from_utf8(&data)?;
+
+            println!("==== Get Request {} ====\n\n{}\n", message.id, text);
+        }
+
+        Ok(())
+    }
+}
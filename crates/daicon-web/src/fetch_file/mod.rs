@@ -0,0 +1,196 @@
+mod service;
+
+use std::time::Duration;
+
+pub use self::service::open_fetch_file;
+
+#[derive(Clone)]
+pub struct FetchFileOptions {
+    timeout: Option<Duration>,
+    retries: u32,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+}
+
+impl FetchFileOptions {
+    /// Set the timeout of a single fetch attempt, or `None` to wait indefinitely.
+    ///
+    /// When the timeout elapses, the transport's fetch is dropped, which aborts it.
+    /// For example, `BrowserTransport` aborts it through its `AbortController`.
+    pub fn timeout(mut self, value: Option<Duration>) -> Self {
+        self.timeout = value;
+        self
+    }
+
+    /// Set how many times a fetch is retried after a transient error, such as a network error,
+    /// a timeout, or a 5xx status.
+    pub fn retries(mut self, value: u32) -> Self {
+        self.retries = value;
+        self
+    }
+
+    /// Set the delay before the first retry.
+    ///
+    /// Every following retry doubles the delay, up to `max_retry_delay`.
+    pub fn retry_delay(mut self, value: Duration) -> Self {
+        self.retry_delay = value;
+        self
+    }
+
+    /// Set the upper bound of the exponential backoff delay between retries.
+    pub fn max_retry_delay(mut self, value: Duration) -> Self {
+        self.max_retry_delay = value;
+        self
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+        self.retry_delay
+            .saturating_mul(factor)
+            .min(self.max_retry_delay)
+    }
+}
+
+impl Default for FetchFileOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(30)),
+            retries: 3,
+            retry_delay: Duration::from_millis(250),
+            max_retry_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::{
+        cell::RefCell,
+        io::{Read, Write},
+        net::TcpListener,
+        rc::Rc,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    use daicon::{protocol::file, Executor};
+    use futures::executor::LocalPool;
+    use stewart::{Handler, Id};
+    use uuid::Uuid;
+
+    use super::{open_fetch_file, FetchFileOptions};
+    use crate::NativeTransport;
+
+    /// Serve one connection per response, in order, recording the request heads.
+    fn serve(responses: Vec<&'static [u8]>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let requests_ref = requests.clone();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+
+                let mut head = Vec::new();
+                let mut byte = [0u8];
+                while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                    head.push(byte[0]);
+                }
+                requests_ref
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8(head).unwrap());
+
+                stream.write_all(response).unwrap();
+            }
+        });
+
+        (format!("http://{}/package", address), requests)
+    }
+
+    /// Fetch file on a local pool, with a native transport.
+    struct Fixture {
+        executor: Executor,
+        pool: LocalPool,
+        file: Handler<file::Request>,
+    }
+
+    impl Fixture {
+        fn new(url: String) -> Self {
+            let executor = Executor::default();
+            let pool = LocalPool::new();
+
+            let transport = NativeTransport::new(pool.spawner());
+            let options = FetchFileOptions::default().retries(0);
+            let file = executor
+                .with_world(|world| {
+                    open_fetch_file(world, Id::none(), url, transport, executor.clone(), options)
+                })
+                .unwrap();
+
+            Self {
+                executor,
+                pool,
+                file,
+            }
+        }
+
+        fn read(&mut self, offset: u64, size: u64) -> Result<Vec<u8>, file::Error> {
+            let result = Rc::new(RefCell::new(None));
+            let result_ref = result.clone();
+            let action = file::ReadAction {
+                offset,
+                size,
+                on_result: Handler::none().map(move |response: file::ReadResponse| {
+                    *result_ref.borrow_mut() = Some(response.result)
+                }),
+            };
+            let request = file::Request {
+                id: Uuid::new_v4(),
+                action: file::Action::Read(action),
+            };
+            self.executor.send(self.file.clone(), request);
+
+            // Fetch tasks hand their result to the world before completing
+            self.pool.run();
+
+            let result = result.borrow_mut().take();
+            result.expect("read didn't respond")
+        }
+    }
+
+    #[test]
+    fn read_range_from_full_response() {
+        // Servers without range support send the entire file
+        let (url, requests) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"a\"\r\n\r\n0123456789",
+        ]);
+        let mut fixture = Fixture::new(url);
+
+        assert_eq!(fixture.read(2, 3).unwrap(), b"234");
+        assert!(requests.lock().unwrap()[0].contains("Range: bytes=2-4\r\n"));
+    }
+
+    #[test]
+    fn read_detects_changed_file() {
+        let (url, requests) = serve(vec![
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 3\r\nETag: \"a\"\r\n\r\n234",
+            // The file changed, so the server ignores the range
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"b\"\r\n\r\nabcdefghij",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 3\r\nETag: \"b\"\r\n\r\ncde",
+        ]);
+        let mut fixture = Fixture::new(url);
+
+        assert_eq!(fixture.read(2, 3).unwrap(), b"234");
+        assert!(matches!(fixture.read(2, 3), Err(file::Error::Changed)));
+
+        // After reporting the change, the new version is read
+        assert_eq!(fixture.read(2, 3).unwrap(), b"cde");
+
+        let requests = requests.lock().unwrap();
+        assert!(!requests[0].contains("If-Range"));
+        assert!(requests[1].contains("If-Range: \"a\"\r\n"));
+        assert!(!requests[2].contains("If-Range"));
+    }
+}
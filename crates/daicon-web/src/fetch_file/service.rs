@@ -0,0 +1,367 @@
+use std::{cell::RefCell, collections::HashMap, ops::Range, rc::Rc};
+
+use anyhow::Error;
+use daicon::{protocol::file, Executor};
+use futures::{
+    future::{self, AbortHandle, Abortable, Either},
+    FutureExt,
+};
+use stewart::{Actor, Context, Handler, Id, World};
+use tracing::{event, instrument, Level};
+use uuid::Uuid;
+
+use crate::{FetchFileOptions, HttpRequest, HttpResponse, Transport, TransportError};
+
+#[instrument("open_fetch_file", skip_all)]
+pub fn open_fetch_file(
+    world: &mut World,
+    id: Id,
+    url: String,
+    transport: impl Transport,
+    executor: Executor,
+    options: FetchFileOptions,
+) -> Result<Handler<file::Request>, Error> {
+    let id = world.create(id, "daicon-fetch-file")?;
+    let handler = Handler::to(id);
+
+    let actor = FetchFile {
+        executor,
+        handler: handler.clone(),
+        remote: Rc::new(Remote {
+            url,
+            transport: Box::new(transport),
+            options,
+            validator: Validator::default(),
+        }),
+
+        pending: HashMap::new(),
+    };
+    world.start(id, actor)?;
+
+    Ok(handler.map(Message::Request))
+}
+
+struct FetchFile {
+    executor: Executor,
+    handler: Handler<Message>,
+    remote: Rc<Remote>,
+
+    pending: HashMap<Uuid, PendingRead>,
+}
+
+struct PendingRead {
+    action: file::ReadAction,
+    abort: AbortHandle,
+}
+
+enum Message {
+    Request(file::Request),
+    FetchResult {
+        id: Uuid,
+        result: Result<Vec<u8>, file::Error>,
+    },
+}
+
+impl Actor for FetchFile {
+    type Message = Message;
+
+    fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+        while let Some(message) = cx.next() {
+            match message {
+                Message::Request(message) => {
+                    self.on_message(world, message);
+                }
+                Message::FetchResult { id, result } => {
+                    self.on_fetch_result(world, id, result);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FetchFile {
+    fn on_message(&mut self, world: &mut World, message: file::Request) {
+        match message.action {
+            file::Action::Read(action) => {
+                self.on_read(message.id, action);
+            }
+            file::Action::Write(action) => {
+                // Report back invalid operation
+                let response = file::WriteResponse {
+                    id: message.id,
+                    result: Err(file::Error::NotSupported),
+                };
+                action.on_result.handle(world, response);
+            }
+            file::Action::Cancel(action) => {
+                self.on_cancel(world, action);
+            }
+        }
+    }
+
+    fn on_read(&mut self, id: Uuid, action: file::ReadAction) {
+        event!(Level::INFO, "received read");
+
+        let range = action.offset..(action.offset + action.size);
+
+        // There's no valid range header for no data, so respond right away
+        if range.is_empty() {
+            let response = file::ReadResponse {
+                id,
+                result: Ok(Vec::new()),
+            };
+            self.executor.queue(action.on_result, response);
+            return;
+        }
+
+        // TODO: Batch fetches, we can do multiple range requests at once
+        let task = do_fetch(
+            self.executor.clone(),
+            self.handler.clone(),
+            id,
+            self.remote.clone(),
+            range,
+        );
+
+        // Aborting drops the task, which drops and so aborts the transport's fetch
+        let (abort, registration) = AbortHandle::new_pair();
+        let task = Abortable::new(task, registration).map(|_| ());
+        self.remote.transport.spawn(task.boxed_local());
+
+        let pending = PendingRead { action, abort };
+        self.pending.insert(id, pending);
+    }
+
+    fn on_cancel(&mut self, world: &mut World, action: file::CancelAction) {
+        // The read may already have completed, in which case there's nothing to do
+        let Some(pending) = self.pending.remove(&action.id) else {
+            event!(Level::DEBUG, id = ?action.id, "no pending read to cancel");
+            return;
+        };
+
+        event!(Level::INFO, id = ?action.id, "cancelling read");
+        pending.abort.abort();
+
+        let message = file::ReadResponse {
+            id: action.id,
+            result: Err(file::Error::Cancelled),
+        };
+        pending.action.on_result.handle(world, message);
+    }
+
+    fn on_fetch_result(
+        &mut self,
+        world: &mut World,
+        id: Uuid,
+        result: Result<Vec<u8>, file::Error>,
+    ) {
+        event!(Level::INFO, "received fetch result");
+
+        // If the read was cancelled, we already responded
+        let Some(pending) = self.pending.remove(&id) else {
+            event!(Level::DEBUG, ?id, "fetch result for cancelled read");
+            return;
+        };
+
+        let message = file::ReadResponse { id, result };
+        pending.action.on_result.handle(world, message);
+    }
+}
+
+/// State of the remote file, shared with fetch tasks.
+struct Remote {
+    url: String,
+    transport: Box<dyn Transport>,
+    options: FetchFileOptions,
+    validator: Validator,
+}
+
+async fn do_fetch(
+    executor: Executor,
+    handler: Handler<Message>,
+    id: Uuid,
+    remote: Rc<Remote>,
+    range: Range<u64>,
+) {
+    let result = fetch_with_retries(&remote, &range).await;
+
+    // Send the data back, the executor takes care of handing it to the world safely
+    executor.send(handler, Message::FetchResult { id, result });
+}
+
+async fn fetch_with_retries(remote: &Remote, range: &Range<u64>) -> Result<Vec<u8>, file::Error> {
+    let mut attempt = 0;
+
+    loop {
+        let result = fetch_attempt(remote, range).await;
+
+        // Retry transient errors, until we run out of attempts
+        match result {
+            Err(FetchError::Transient(error)) if attempt < remote.options.retries => {
+                let delay = remote.options.backoff(attempt);
+                event!(
+                    Level::WARN,
+                    ?error,
+                    ?delay,
+                    attempt,
+                    "fetch failed, retrying"
+                );
+                attempt += 1;
+
+                remote.transport.sleep(delay).await;
+            }
+            Err(FetchError::Transient(error) | FetchError::Fatal(error)) => return Err(error),
+            Ok(data) => return Ok(data),
+        }
+    }
+}
+
+enum FetchError {
+    /// Error that may succeed if retried.
+    Transient(file::Error),
+    Fatal(file::Error),
+}
+
+async fn fetch_attempt(remote: &Remote, range: &Range<u64>) -> Result<Vec<u8>, FetchError> {
+    event!(Level::INFO, "fetching data");
+
+    let mut headers = Vec::new();
+    let range_header = format!("bytes={}-{}", range.start, range.end - 1);
+    event!(Level::TRACE, range = range_header);
+    headers.push(("Range".to_string(), range_header));
+
+    // If we've seen the file before, only accept ranges of that same representation
+    let sent = remote.validator.get();
+    if let Some(value) = &sent {
+        headers.push(("If-Range".to_string(), value.clone()));
+    }
+
+    let request = HttpRequest {
+        url: remote.url.clone(),
+        headers,
+        timeout: remote.options.timeout,
+    };
+    let fetch = remote.transport.fetch(request);
+
+    // Abort the attempt if it takes too long, dropping the fetch aborts it
+    let result = match remote.options.timeout {
+        Some(duration) => match future::select(fetch, remote.transport.sleep(duration)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => return Err(FetchError::Transient(file::Error::TimedOut)),
+        },
+        None => fetch.await,
+    };
+
+    let response = result.map_err(|error| match error {
+        TransportError::Network { .. } => FetchError::Transient(internal(error)),
+        TransportError::InternalError { .. } => FetchError::Fatal(internal(error)),
+    })?;
+
+    // The range starts past the end of the file, which is a short read of no data
+    if response.status == 416 {
+        return Ok(Vec::new());
+    }
+
+    check_status(response.status)?;
+    remote.validator.check(sent, &response)?;
+
+    Ok(response_range(response, range))
+}
+
+/// Get the requested range out of a successful response.
+///
+/// Reads past the end of the file are short, as are the responses for them.
+fn response_range(response: HttpResponse, range: &Range<u64>) -> Vec<u8> {
+    let mut body = response.body;
+
+    // Servers that don't support ranges send the entire file
+    if response.status == 200 {
+        let start = (range.start as usize).min(body.len());
+        body.drain(..start);
+    }
+
+    body.truncate((range.end - range.start) as usize);
+    body
+}
+
+/// Validator of the remote representation, used to detect the file changing between requests.
+///
+/// The first response's validator is recorded, and sent as `If-Range` on later requests.
+#[derive(Default)]
+struct Validator {
+    value: RefCell<Option<String>>,
+}
+
+impl Validator {
+    fn get(&self) -> Option<String> {
+        self.value.borrow().clone()
+    }
+
+    fn check(&self, sent: Option<String>, response: &HttpResponse) -> Result<(), FetchError> {
+        let received = response_validator(response);
+
+        let changed = match (&sent, &received) {
+            // Also catches servers that don't support `If-Range`
+            (Some(sent), Some(received)) => sent != received,
+            // The server ignores the range if `If-Range` doesn't match, returning the entire file,
+            // servers that don't support ranges at all still send the same validator however
+            (Some(_), None) => response.status == 200,
+            _ => false,
+        };
+
+        if changed {
+            event!(Level::WARN, ?sent, ?received, "remote file changed");
+
+            // Forget the old representation, so the next request records the new one
+            self.value.borrow_mut().take();
+            return Err(FetchError::Fatal(file::Error::Changed));
+        }
+
+        // Record the validator, if this is the first time we see it
+        if sent.is_none() {
+            let mut value = self.value.borrow_mut();
+            if value.is_none() {
+                *value = received;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the validator usable with `If-Range`, which requires a strong `ETag` or `Last-Modified`.
+///
+/// Cross-origin, the server has to list `ETag` in `Access-Control-Expose-Headers`, or browsers
+/// only show us `Last-Modified`.
+fn response_validator(response: &HttpResponse) -> Option<String> {
+    let etag = response.header("ETag");
+    if let Some(etag) = etag.filter(|value| !value.starts_with("W/")) {
+        return Some(etag.to_string());
+    }
+
+    response.header("Last-Modified").map(str::to_string)
+}
+
+fn check_status(status: u16) -> Result<(), FetchError> {
+    if (200..300).contains(&status) {
+        return Ok(());
+    }
+
+    let error = file::Error::InternalError {
+        error: format!("fetch failed with status {}", status),
+    };
+
+    // Statuses that are worth retrying, as the server may recover
+    match status {
+        408 | 425 | 429 | 500 | 502 | 503 | 504 => Err(FetchError::Transient(error)),
+        _ => Err(FetchError::Fatal(error)),
+    }
+}
+
+fn internal(error: TransportError) -> file::Error {
+    file::Error::InternalError {
+        error: error.to_string(),
+    }
+}
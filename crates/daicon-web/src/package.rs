@@ -0,0 +1,122 @@
+use std::fmt::Display;
+
+use daicon::{open_file_source, protocol::source, Executor, FileSourceOptions, SourceClient};
+use futures::future;
+use js_sys::{Array, Promise, Uint8Array};
+use stewart::Id;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+use crate::{open_fetch_file, BrowserTransport, FetchFileOptions};
+
+/// Daicon package fetched from a URL, for use from JavaScript.
+#[wasm_bindgen]
+pub struct DaiconPackage {
+    client: SourceClient,
+}
+
+#[wasm_bindgen]
+impl DaiconPackage {
+    /// Open the daicon package at the given URL.
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: String) -> Result<DaiconPackage, JsError> {
+        let executor = Executor::default();
+
+        let source = executor
+            .with_world(|world| {
+                let file = open_fetch_file(
+                    world,
+                    Id::none(),
+                    url,
+                    BrowserTransport::new(),
+                    executor.clone(),
+                    FetchFileOptions::default(),
+                )?;
+                let options = FileSourceOptions::default().open_table(0);
+                open_file_source(world, Id::none(), file, options)
+            })
+            .map_err(|error| JsError::new(&error.to_string()))?;
+
+        let client = SourceClient::new(executor, source);
+        Ok(Self { client })
+    }
+
+    /// Get the data of an entry.
+    #[wasm_bindgen(unchecked_return_type = "Promise<Uint8Array>")]
+    pub fn get(&self, id: u32) -> Promise {
+        let data = self.client.get(source::Id(id));
+
+        future_to_promise(async move {
+            let data = data.await.map_err(to_js)?;
+            Ok(Uint8Array::from(data.as_slice()).into())
+        })
+    }
+
+    /// Get the data of multiple entries, fetched all at once.
+    #[wasm_bindgen(js_name = getMany, unchecked_return_type = "Promise<Uint8Array[]>")]
+    pub fn get_many(&self, ids: Vec<u32>) -> Promise {
+        let futures: Vec<_> = ids
+            .into_iter()
+            .map(|id| self.client.get(source::Id(id)))
+            .collect();
+
+        future_to_promise(async move {
+            let results = future::join_all(futures).await;
+
+            let array = Array::new();
+            for data in results {
+                let data = data.map_err(to_js)?;
+                array.push(&Uint8Array::from(data.as_slice()));
+            }
+
+            Ok(array.into())
+        })
+    }
+
+    /// List the IDs of all entries.
+    #[wasm_bindgen(unchecked_return_type = "Promise<number[]>")]
+    pub fn list(&self) -> Promise {
+        let ids = self.client.list();
+
+        future_to_promise(async move {
+            let ids = ids.await.map_err(to_js)?;
+
+            let array = Array::new();
+            for id in ids {
+                array.push(&JsValue::from(id.0));
+            }
+
+            Ok(array.into())
+        })
+    }
+
+    /// Get the metadata of an entry, without fetching its data.
+    #[wasm_bindgen(unchecked_return_type = "Promise<DaiconStat>")]
+    pub fn stat(&self, id: u32) -> Promise {
+        let stat = self.client.stat(source::Id(id));
+
+        future_to_promise(async move {
+            let stat = stat.await.map_err(to_js)?;
+
+            let stat = DaiconStat {
+                id,
+                size: stat.size as f64,
+            };
+            Ok(stat.into())
+        })
+    }
+}
+
+/// Metadata of an entry in a daicon package.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct DaiconStat {
+    /// ID of the entry.
+    pub id: u32,
+    /// Size of the entry's data in bytes.
+    pub size: f64,
+}
+
+fn to_js(error: impl Display) -> JsValue {
+    JsError::new(&error.to_string()).into()
+}
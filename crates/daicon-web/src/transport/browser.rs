@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use futures::{future::LocalBoxFuture, FutureExt};
+use js_sys::{ArrayBuffer, Promise, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{AbortController, Headers, Request, RequestInit, RequestMode, Response};
+
+use crate::{HttpRequest, HttpResponse, Transport, TransportError};
+
+/// Transport using the browser's JS `fetch`.
+///
+/// Dropping a pending fetch aborts it through its `AbortController`.
+#[derive(Default)]
+pub struct BrowserTransport {
+    _private: (),
+}
+
+impl BrowserTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Transport for BrowserTransport {
+    fn fetch(
+        &self,
+        request: HttpRequest,
+    ) -> LocalBoxFuture<'static, Result<HttpResponse, TransportError>> {
+        do_fetch(request).boxed_local()
+    }
+
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+        do_sleep(duration).boxed_local()
+    }
+
+    fn spawn(&self, task: LocalBoxFuture<'static, ()>) {
+        spawn_local(task);
+    }
+}
+
+async fn do_fetch(request: HttpRequest) -> Result<HttpResponse, TransportError> {
+    let window = web_sys::window().ok_or_else(|| internal("failed to get window"))?;
+
+    let headers = Headers::new().map_err(js_internal)?;
+    for (name, value) in &request.headers {
+        headers.append(name, value).map_err(js_internal)?;
+    }
+
+    // Abort the fetch if this future gets dropped before completing
+    let controller = AbortController::new().map_err(js_internal)?;
+    let mut guard = AbortGuard(Some(controller));
+    let signal = guard.0.as_ref().map(|controller| controller.signal());
+
+    let opts = RequestInit::new();
+    opts.set_method("GET");
+    opts.set_mode(RequestMode::Cors);
+    opts.set_headers(&headers);
+    opts.set_signal(signal.as_ref());
+
+    let js_request = Request::new_with_str_and_init(&request.url, &opts).map_err(js_internal)?;
+    let response = window.fetch_with_request(&js_request);
+
+    // Await the response, a rejection here means a network error
+    let response = JsFuture::from(response).await.map_err(js_network)?;
+    let response: Response = response.dyn_into().map_err(js_internal)?;
+
+    let headers = read_headers(&response.headers())?;
+
+    // Await all the response data
+    let body = response.array_buffer().map_err(js_internal)?;
+    let body = JsFuture::from(body).await.map_err(js_network)?;
+    let body: ArrayBuffer = body.dyn_into().map_err(js_internal)?;
+    let body = Uint8Array::new(&body).to_vec();
+
+    // We're done, no need to abort anymore
+    guard.0.take();
+
+    let response = HttpResponse {
+        status: response.status(),
+        headers,
+        body,
+    };
+    Ok(response)
+}
+
+fn read_headers(headers: &Headers) -> Result<Vec<(String, String)>, TransportError> {
+    let mut values = Vec::new();
+
+    // Every entry is a `[name, value]` array
+    for entry in headers.entries() {
+        let entry: js_sys::Array = entry
+            .map_err(js_internal)?
+            .dyn_into()
+            .map_err(js_internal)?;
+        let name = entry.get(0).as_string().unwrap_or_default();
+        let value = entry.get(1).as_string().unwrap_or_default();
+        values.push((name, value));
+    }
+
+    Ok(values)
+}
+
+async fn do_sleep(duration: Duration) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+
+    let millis = duration.as_millis().min(i32::MAX as u128) as i32;
+    let promise = Promise::new(&mut |resolve, _reject| {
+        let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis);
+    });
+
+    let _ = JsFuture::from(promise).await;
+}
+
+struct AbortGuard(Option<AbortController>);
+
+impl Drop for AbortGuard {
+    fn drop(&mut self) {
+        if let Some(controller) = self.0.take() {
+            controller.abort();
+        }
+    }
+}
+
+fn internal(error: &str) -> TransportError {
+    TransportError::InternalError {
+        error: error.to_string(),
+    }
+}
+
+fn js_internal(error: impl Into<JsValue>) -> TransportError {
+    TransportError::InternalError {
+        error: format!("{:?}", error.into()),
+    }
+}
+
+fn js_network(error: JsValue) -> TransportError {
+    TransportError::Network {
+        error: format!("{:?}", error),
+    }
+}
@@ -0,0 +1,64 @@
+mod browser;
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+
+use std::time::Duration;
+
+use futures::future::LocalBoxFuture;
+use thiserror::Error;
+
+pub use self::browser::BrowserTransport;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::native::NativeTransport;
+
+/// HTTP layer used by the fetch file to reach the remote file.
+///
+/// The fetch file only uses this to perform `GET` requests, timeouts and retries are handled by
+/// the fetch file itself.
+pub trait Transport: 'static {
+    /// Perform a `GET` request, resolving with the entire response.
+    ///
+    /// Dropping the returned future should abort the request, if the transport supports it.
+    fn fetch(
+        &self,
+        request: HttpRequest,
+    ) -> LocalBoxFuture<'static, Result<HttpResponse, TransportError>>;
+
+    /// Resolve after the given duration has elapsed.
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()>;
+
+    /// Spawn a task on the executor driving this transport's futures.
+    fn spawn(&self, task: LocalBoxFuture<'static, ()>);
+}
+
+pub struct HttpRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// Timeout of the attempt, transports that block should apply it to their own I/O.
+    pub timeout: Option<Duration>,
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Get the value of a header, by case-insensitive name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TransportError {
+    /// The server couldn't be reached, or the connection failed, retrying may succeed.
+    #[error("network error: {error}")]
+    Network { error: String },
+    #[error("internal error: {error}")]
+    InternalError { error: String },
+}
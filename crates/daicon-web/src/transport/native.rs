@@ -0,0 +1,352 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    thread,
+    time::Duration,
+};
+
+use futures::{
+    channel::oneshot, executor::LocalSpawner, future::LocalBoxFuture, task::LocalSpawnExt,
+    FutureExt,
+};
+use tracing::{event, Level};
+
+use crate::{HttpRequest, HttpResponse, Transport, TransportError};
+
+/// Transport using blocking HTTP/1.1 over a std `TcpStream`, on a thread per request.
+///
+/// Only plain `http://` URLs are supported.
+/// Tasks are spawned on a `futures` `LocalPool`, which has to be run on the same thread as the
+/// world.
+pub struct NativeTransport {
+    spawner: LocalSpawner,
+}
+
+impl NativeTransport {
+    pub fn new(spawner: LocalSpawner) -> Self {
+        Self { spawner }
+    }
+}
+
+impl Transport for NativeTransport {
+    fn fetch(
+        &self,
+        request: HttpRequest,
+    ) -> LocalBoxFuture<'static, Result<HttpResponse, TransportError>> {
+        let (sender, receiver) = oneshot::channel();
+
+        // If the future gets dropped the request still completes, but the result is discarded
+        thread::spawn(move || {
+            let _ = sender.send(blocking_fetch(&request));
+        });
+
+        async move {
+            receiver.await.unwrap_or_else(|_| {
+                Err(TransportError::InternalError {
+                    error: "request thread stopped".to_string(),
+                })
+            })
+        }
+        .boxed_local()
+    }
+
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+        let (sender, receiver) = oneshot::channel();
+
+        thread::spawn(move || {
+            thread::sleep(duration);
+            let _ = sender.send(());
+        });
+
+        async move {
+            let _ = receiver.await;
+        }
+        .boxed_local()
+    }
+
+    fn spawn(&self, task: LocalBoxFuture<'static, ()>) {
+        if let Err(error) = self.spawner.spawn_local(task) {
+            event!(Level::ERROR, ?error, "failed to spawn task");
+        }
+    }
+}
+
+fn blocking_fetch(request: &HttpRequest) -> Result<HttpResponse, TransportError> {
+    let (authority, host, port, path) = parse_url(&request.url)?;
+
+    let stream = connect(host, port, request.timeout)?;
+
+    // The fetch future gets dropped on timeout, but this thread has to stop on its own
+    stream.set_read_timeout(request.timeout).map_err(network)?;
+    stream.set_write_timeout(request.timeout).map_err(network)?;
+
+    // Send the request, we don't re-use connections so tell the server to close it
+    let mut head = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n",
+        path, authority
+    );
+    for (name, value) in &request.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    (&stream).write_all(head.as_bytes()).map_err(network)?;
+
+    let mut reader = BufReader::new(stream);
+
+    // Read the status line, for example "HTTP/1.1 206 Partial Content"
+    let line = read_line(&mut reader)?;
+    let status = line
+        .split(' ')
+        .nth(1)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| internal(format!("invalid status line \"{}\"", line)))?;
+
+    // Read headers until the empty line
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| internal(format!("invalid header \"{}\"", line)))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut response = HttpResponse {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+
+    // Read the body, in whichever way the server decided to send it
+    let chunked = response
+        .header("Transfer-Encoding")
+        .map(|value| value.eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false);
+    let length = response
+        .header("Content-Length")
+        .and_then(|value| value.parse::<usize>().ok());
+
+    response.body = match (chunked, length) {
+        (true, _) => read_chunked(&mut reader)?,
+        (false, Some(length)) => read_exact(&mut reader, length)?,
+        (false, None) => {
+            let mut body = Vec::new();
+            reader.read_to_end(&mut body).map_err(network)?;
+            body
+        }
+    };
+
+    Ok(response)
+}
+
+fn connect(host: &str, port: u16, timeout: Option<Duration>) -> Result<TcpStream, TransportError> {
+    let Some(timeout) = timeout else {
+        return TcpStream::connect((host, port)).map_err(network);
+    };
+
+    // Connecting with a timeout needs a resolved address, try each until one succeeds
+    let mut last_error = None;
+    for address in (host, port).to_socket_addrs().map_err(network)? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(match last_error {
+        Some(error) => network(error),
+        None => internal(format!("no addresses found for host \"{}\"", host)),
+    })
+}
+
+/// Read exactly `length` bytes, growing the buffer as data arrives rather than trusting the
+/// server's length up front.
+fn read_exact(reader: &mut impl Read, length: usize) -> Result<Vec<u8>, TransportError> {
+    let mut data = Vec::new();
+    reader
+        .take(length as u64)
+        .read_to_end(&mut data)
+        .map_err(network)?;
+
+    if data.len() != length {
+        return Err(TransportError::Network {
+            error: "connection closed unexpectedly".to_string(),
+        });
+    }
+
+    Ok(data)
+}
+
+/// Split an `http://` URL into its authority, host, port, and path.
+fn parse_url(url: &str) -> Result<(&str, &str, u16, &str), TransportError> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        internal(format!(
+            "unsupported url \"{}\", only http is supported",
+            url
+        ))
+    })?;
+
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => {
+            let port = port
+                .parse()
+                .map_err(|_| internal(format!("invalid port in url \"{}\"", url)))?;
+            (host, port)
+        }
+        _ => (authority, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    Ok((authority, host, port, path))
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> Result<Vec<u8>, TransportError> {
+    let mut body = Vec::new();
+
+    loop {
+        // Every chunk starts with its size in hexadecimal, optionally followed by extensions
+        let line = read_line(reader)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| internal(format!("invalid chunk size \"{}\"", line)))?;
+
+        // The last chunk is empty, followed by optional trailers
+        if size == 0 {
+            while !read_line(reader)?.is_empty() {}
+            break;
+        }
+
+        body.extend(read_exact(reader, size)?);
+
+        // Every chunk's data is terminated by a line break
+        read_line(reader)?;
+    }
+
+    Ok(body)
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String, TransportError> {
+    let mut line = String::new();
+    let read = reader.read_line(&mut line).map_err(network)?;
+
+    if read == 0 {
+        return Err(TransportError::Network {
+            error: "connection closed unexpectedly".to_string(),
+        });
+    }
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn network(error: std::io::Error) -> TransportError {
+    TransportError::Network {
+        error: error.to_string(),
+    }
+}
+
+fn internal(error: String) -> TransportError {
+    TransportError::InternalError { error }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::blocking_fetch;
+    use crate::{HttpRequest, TransportError};
+
+    /// Serve a single connection, reading the request head and replying with `response`.
+    fn serve_once(response: &'static [u8], hold: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut head = Vec::new();
+            let mut byte = [0u8];
+            while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                head.push(byte[0]);
+            }
+
+            stream.write_all(response).unwrap();
+            thread::sleep(hold);
+        });
+
+        format!("http://{}/file", address)
+    }
+
+    fn request(url: String, timeout: Option<Duration>) -> HttpRequest {
+        HttpRequest {
+            url,
+            headers: vec![("Range".to_string(), "bytes=0-3".to_string())],
+            timeout,
+        }
+    }
+
+    #[test]
+    fn fetch_content_length() {
+        let url = serve_once(
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 4\r\nETag: \"a\"\r\n\r\nabcd",
+            Duration::ZERO,
+        );
+
+        let response = blocking_fetch(&request(url, None)).unwrap();
+
+        assert_eq!(response.status, 206);
+        assert_eq!(response.header("etag"), Some("\"a\""));
+        assert_eq!(response.body, b"abcd");
+    }
+
+    #[test]
+    fn fetch_chunked() {
+        let url = serve_once(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nab\r\n2;x=y\r\ncd\r\n0\r\n\r\n",
+            Duration::ZERO,
+        );
+
+        let response = blocking_fetch(&request(url, None)).unwrap();
+
+        assert_eq!(response.body, b"abcd");
+    }
+
+    #[test]
+    fn fetch_short_body_fails_without_allocating_length() {
+        let url = serve_once(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1000000000000\r\n\r\nabcd",
+            Duration::ZERO,
+        );
+
+        let result = blocking_fetch(&request(url, None));
+
+        assert!(matches!(result, Err(TransportError::Network { .. })));
+    }
+
+    #[test]
+    fn fetch_times_out_on_stalled_body() {
+        let url = serve_once(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\nabcd",
+            Duration::from_secs(5),
+        );
+
+        let start = Instant::now();
+        let result = blocking_fetch(&request(url, Some(Duration::from_millis(200))));
+
+        assert!(matches!(result, Err(TransportError::Network { .. })));
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    rc::Rc,
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Condvar, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Error;
+use stewart::{Handler, World};
+use tracing::{event, Level};
+
+/// Executor owning a `World`, that safely receives completions from outside of it.
+///
+/// Completions are queued, and handed to the world the next time the executor runs.
+/// This makes it safe to complete from anywhere, including while the world is already running.
+///
+/// How the executor is run is up to the platform:
+/// - On a browser event loop, `send` runs the executor right away.
+/// - On a native thread, alternate `run_until_idle` and `wait`.
+/// - In a test harness, call `run_until_idle` whenever needed.
+#[derive(Clone)]
+pub struct Executor {
+    shared: Rc<Shared>,
+}
+
+struct Shared {
+    world: RefCell<World>,
+    queue: RefCell<VecDeque<Completion>>,
+    remotes: RefCell<Vec<Drain>>,
+    signal: Arc<Signal>,
+}
+
+type Completion = Box<dyn FnOnce(&mut World)>;
+
+/// Drains a remote channel into the world, returning `None` when the channel is disconnected.
+type Drain = Box<dyn FnMut(&mut World) -> Option<bool>>;
+
+impl Executor {
+    pub fn new(world: World) -> Self {
+        let shared = Shared {
+            world: RefCell::new(world),
+            queue: RefCell::new(VecDeque::new()),
+            remotes: RefCell::new(Vec::new()),
+            signal: Arc::new(Signal::default()),
+        };
+
+        Self {
+            shared: Rc::new(shared),
+        }
+    }
+
+    /// Access the world directly, for example to start actors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the world is already in use, such as from within an actor.
+    /// Actors should use the world they are given instead.
+    pub fn with_world<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce(&mut World) -> R,
+    {
+        let mut world = self.shared.world.borrow_mut();
+        callback(&mut world)
+    }
+
+    /// Queue a message to a handler, without running the executor.
+    pub fn queue<M>(&self, handler: Handler<M>, message: M)
+    where
+        M: 'static,
+    {
+        let completion = move |world: &mut World| handler.handle(world, message);
+        self.shared
+            .queue
+            .borrow_mut()
+            .push_back(Box::new(completion));
+    }
+
+    /// Queue a message to a handler, and run the executor if it isn't already running.
+    pub fn send<M>(&self, handler: Handler<M>, message: M)
+    where
+        M: 'static,
+    {
+        self.queue(handler, message);
+
+        if let Err(error) = self.run_until_idle() {
+            event!(Level::ERROR, ?error, "failed to run executor");
+        }
+    }
+
+    /// Create a sender, that can send messages to a handler from other threads.
+    pub fn remote<M>(&self, handler: Handler<M>) -> RemoteSender<M>
+    where
+        M: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        let drain = move |world: &mut World| drain(world, &receiver, &handler);
+        self.shared.remotes.borrow_mut().push(Box::new(drain));
+
+        RemoteSender {
+            sender,
+            signal: self.shared.signal.clone(),
+        }
+    }
+
+    /// Process all queued completions and pending messages, until none are left.
+    ///
+    /// If the executor is already running, this does nothing, as the running call will pick up
+    /// anything newly queued.
+    pub fn run_until_idle(&self) -> Result<(), Error> {
+        let Ok(mut world) = self.shared.world.try_borrow_mut() else {
+            return Ok(());
+        };
+
+        loop {
+            world.run_until_idle()?;
+
+            // Anything sent remotely after this will signal again, so we don't miss it when waiting
+            self.shared.signal.reset();
+            let mut progress = false;
+
+            // Hand all local completions to the world
+            loop {
+                let next = self.shared.queue.borrow_mut().pop_front();
+                let Some(completion) = next else {
+                    break;
+                };
+
+                completion(&mut world);
+                progress = true;
+            }
+
+            // Hand all remote completions to the world, handlers may create new remotes in the
+            // meantime so we can't keep this borrowed
+            let mut remotes = std::mem::take(&mut *self.shared.remotes.borrow_mut());
+            remotes.retain_mut(|drain| match drain(&mut world) {
+                Some(drained) => {
+                    progress |= drained;
+                    true
+                }
+                None => false,
+            });
+            self.shared.remotes.borrow_mut().append(&mut remotes);
+
+            if !progress {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Block the current thread until a remote completion is available.
+    pub fn wait(&self) {
+        self.shared.signal.wait(None);
+    }
+
+    /// Block the current thread until a remote completion is available, or the timeout elapses.
+    ///
+    /// Returns true if a remote completion is available.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.shared.signal.wait(Some(timeout))
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new(World::default())
+    }
+}
+
+fn drain<M>(world: &mut World, receiver: &Receiver<M>, handler: &Handler<M>) -> Option<bool>
+where
+    M: 'static,
+{
+    let mut drained = false;
+
+    loop {
+        match receiver.try_recv() {
+            Ok(message) => {
+                handler.handle(world, message);
+                drained = true;
+            }
+            Err(TryRecvError::Empty) => return Some(drained),
+            // Still report what we've drained, we'll clean up the next time
+            Err(TryRecvError::Disconnected) if drained => return Some(drained),
+            Err(TryRecvError::Disconnected) => return None,
+        }
+    }
+}
+
+/// Thread-safe sender of messages to a handler in an `Executor`'s world.
+pub struct RemoteSender<M> {
+    sender: mpsc::Sender<M>,
+    signal: Arc<Signal>,
+}
+
+impl<M> RemoteSender<M> {
+    /// Send a message, waking up the executor's thread if it's waiting.
+    ///
+    /// Returns the message back if the executor has been dropped.
+    pub fn send(&self, message: M) -> Result<(), M> {
+        self.sender.send(message).map_err(|error| error.0)?;
+        self.signal.notify();
+        Ok(())
+    }
+}
+
+impl<M> Clone for RemoteSender<M> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            signal: self.signal.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Signal {
+    pending: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Signal {
+    fn notify(&self) {
+        *self.pending.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+
+    fn reset(&self) {
+        *self.pending.lock().unwrap() = false;
+    }
+
+    fn wait(&self, timeout: Option<Duration>) -> bool {
+        let pending = self.pending.lock().unwrap();
+
+        let pending = match timeout {
+            Some(timeout) => {
+                self.condvar
+                    .wait_timeout_while(pending, timeout, |pending| !*pending)
+                    .unwrap()
+                    .0
+            }
+            None => self
+                .condvar
+                .wait_while(pending, |pending| !*pending)
+                .unwrap(),
+        };
+
+        *pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, thread, time::Duration};
+
+    use stewart::Handler;
+
+    use super::Executor;
+
+    /// Create a handler that records every message it receives.
+    fn recorder<M: 'static>() -> (Handler<M>, Rc<RefCell<Vec<M>>>) {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let received_ref = received.clone();
+        let handler = Handler::none().map(move |message| received_ref.borrow_mut().push(message));
+        (handler, received)
+    }
+
+    #[test]
+    fn queue_waits_for_run() {
+        let executor = Executor::default();
+        let (handler, received) = recorder();
+
+        executor.queue(handler.clone(), 1);
+        assert!(received.borrow().is_empty());
+
+        executor.run_until_idle().unwrap();
+        assert_eq!(*received.borrow(), [1]);
+
+        executor.send(handler, 2);
+        assert_eq!(*received.borrow(), [1, 2]);
+    }
+
+    #[test]
+    fn send_while_running_is_picked_up() {
+        let executor = Executor::default();
+        let (handler, received) = recorder();
+
+        // The nested send can't run the executor, so the running call has to pick it up
+        let nested = executor.clone();
+        let forward = Handler::none().map(move |message: u32| {
+            nested.send(handler.clone(), message);
+        });
+        executor.send(forward, 1);
+
+        assert_eq!(*received.borrow(), [1]);
+    }
+
+    #[test]
+    fn remote_send_wakes_wait() {
+        let executor = Executor::default();
+        let (handler, received) = recorder();
+        let remote = executor.remote(handler);
+
+        let thread = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            remote.send(1).unwrap();
+        });
+
+        // Remote messages only arrive after being woken up and running
+        executor.wait();
+        assert!(received.borrow().is_empty());
+        executor.run_until_idle().unwrap();
+        assert_eq!(*received.borrow(), [1]);
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn wait_timeout_without_remote_send() {
+        let executor = Executor::default();
+        let (handler, _received) = recorder::<u32>();
+        let _remote = executor.remote(handler);
+
+        assert!(!executor.wait_timeout(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn remote_send_after_executor_dropped_fails() {
+        let executor = Executor::default();
+        let (handler, _received) = recorder();
+        let remote = executor.remote(handler);
+
+        drop(executor);
+
+        assert_eq!(remote.send(1), Err(1));
+    }
+}
@@ -6,7 +6,11 @@ use stewart::{Actor, Context, Handler, Id, World};
 use tracing::{event, instrument, Level};
 use uuid::Uuid;
 
-use crate::{file_source::table::Table, protocol::file, FileSourceOptions};
+use crate::{
+    file_source::table::{OnFlush, Table},
+    protocol::{file, source},
+    FileSourceOptions,
+};
 
 // TODO: Needs a lot of cleanup
 // TODO: With stewart 0.9 mailboxes no longer needs to be its own actor.
@@ -19,18 +23,37 @@ pub struct Request {
 pub enum Action {
     Get(GetAction),
     Set(SetAction),
+    List(ListAction),
+    Remove(RemoveAction),
+    /// Discard all cached tables, and read them again from the file.
+    Reload,
 }
 
+/// Offset and size of an entry, `None` if it doesn't exist.
+pub type Location = Option<(u64, u32)>;
+
+/// Find the location of an entry.
 pub struct GetAction {
     pub id: FileId,
-    pub on_result: Handler<(Uuid, u64, u32)>,
+    pub on_result: Handler<(Uuid, Result<Location, source::Error>)>,
 }
 
+/// Set the location of an entry, replacing the existing index if the ID is already in a table.
 pub struct SetAction {
     pub id: FileId,
     pub offset: u64,
     pub size: u32,
-    pub on_result: Handler<Uuid>,
+    pub on_result: OnFlush,
+}
+
+pub struct ListAction {
+    pub on_result: Handler<(Uuid, Result<Vec<FileId>, source::Error>)>,
+}
+
+/// Remove an entry, responding with `false` if it doesn't exist.
+pub struct RemoveAction {
+    pub id: FileId,
+    pub on_result: Handler<(Uuid, Result<bool, source::Error>)>,
 }
 
 #[instrument("start_file_indices", skip_all)]
@@ -47,21 +70,27 @@ pub fn start(
 
     let mut tables = Vec::new();
     let mut pending_read = None;
+    let mut pending_flush = HashMap::new();
 
     // TODO: Respond with validation results, success of open or create.
 
     if let Some(offset) = options.open_table {
         // Start opening by reading the first table
-        pending_read = Some(offset);
-        read_table(world, &file, handler.clone(), offset)?;
+        let id = read_table(world, &file, handler.clone(), offset)?;
+        pending_read = Some(PendingRead { id, offset });
     } else {
-        // Write a table immediately
-        // TODO: This currently always writes at 0, this is not correct and will in the future
-        // fail on an empty file. (it already should following the protocol)
+        // Write a table immediately, letting the file allocate where it goes
         let table = Table::new(options.allocate_capacity);
-        write_table(world, &file, &table, Handler::none())?;
+        let on_result = handler.clone().map(Message::WriteResult);
+        let id = write_table(world, &file, &table, None, on_result)?;
 
         // Track the table we just wrote
+        let flush = PendingFlush {
+            table: tables.len(),
+            allocate: true,
+            on_result: Vec::new(),
+        };
+        pending_flush.insert(id, flush);
         tables.push(table);
     }
 
@@ -69,13 +98,17 @@ pub fn start(
     let actor = Service {
         sender: handler.clone(),
         file,
+        open_table: options.open_table,
 
-        tables: Vec::new(),
+        tables,
         pending_read,
-        pending_flush: HashMap::new(),
+        pending_flush,
+        failure: None,
 
         get_tasks: HashMap::new(),
         set_tasks: HashMap::new(),
+        list_tasks: HashMap::new(),
+        remove_tasks: HashMap::new(),
     };
     world.start(id, actor)?;
 
@@ -85,16 +118,38 @@ pub fn start(
 struct Service {
     sender: Handler<Message>,
     file: Handler<file::Request>,
+    open_table: Option<u64>,
 
     tables: Vec<Table>,
 
-    /// If set, the service is currently still reading tables at the given offset.
-    pending_read: Option<u64>,
-    pending_flush: HashMap<Uuid, Vec<(Uuid, Handler<Uuid>)>>,
+    /// If set, the service is currently still reading tables.
+    pending_read: Option<PendingRead>,
+    pending_flush: HashMap<Uuid, PendingFlush>,
+
+    /// If set, reading or writing tables failed, and every action fails with this reason.
+    failure: Option<String>,
 
     // Ongoing tracked actions
     get_tasks: HashMap<Uuid, GetAction>,
     set_tasks: HashMap<Uuid, SetAction>,
+    list_tasks: HashMap<Uuid, ListAction>,
+    remove_tasks: HashMap<Uuid, RemoveAction>,
+}
+
+/// Read in progress of a table in the chain.
+struct PendingRead {
+    /// Identifier of the read request, responses to older requests are stale.
+    id: Uuid,
+    offset: u64,
+}
+
+/// Flush in progress of a table.
+struct PendingFlush {
+    /// Index of the table in `tables`.
+    table: usize,
+    /// If true, this is the write allocating a new table.
+    allocate: bool,
+    on_result: Vec<(Uuid, OnFlush)>,
 }
 
 enum Message {
@@ -108,21 +163,33 @@ impl Actor for Service {
 
     fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
         while let Some(message) = cx.next() {
-            match message {
-                Message::Request(message) => self.on_message(message),
-                Message::ReadResult(message) => self.on_read_result(world, message)?,
-                Message::WriteResult(message) => self.on_write_result(world, message)?,
+            // After failing, results of reads and writes still in flight don't matter anymore
+            let result = match message {
+                Message::Request(message) => self.on_message(world, message),
+                _ if self.failure.is_some() => Ok(()),
+                Message::ReadResult(message) => self.on_read_result(world, message),
+                Message::WriteResult(message) => self.on_write_result(world, message),
+            };
+
+            // Keep running, so requesters get the reason rather than their request being dropped
+            if let Err(error) = result {
+                event!(Level::ERROR, ?error, "failed to read or write tables");
+                self.failure.get_or_insert_with(|| error.to_string());
             }
         }
 
-        self.update_tasks(world);
+        if self.failure.is_some() {
+            self.fail_tasks(world);
+        } else {
+            self.update_tasks(world);
+        }
 
         Ok(())
     }
 }
 
 impl Service {
-    fn on_message(&mut self, message: Request) {
+    fn on_message(&mut self, world: &mut World, message: Request) -> Result<(), Error> {
         match message.action {
             Action::Get(action) => {
                 event!(Level::DEBUG, id = ?action.id, "received get");
@@ -132,7 +199,41 @@ impl Service {
                 event!(Level::DEBUG, id = ?action.id, "received set");
                 self.set_tasks.insert(message.id, action);
             }
+            Action::List(action) => {
+                event!(Level::DEBUG, "received list");
+                self.list_tasks.insert(message.id, action);
+            }
+            Action::Remove(action) => {
+                event!(Level::DEBUG, id = ?action.id, "received remove");
+                self.remove_tasks.insert(message.id, action);
+            }
+            Action::Reload => {
+                self.reload(world)?;
+            }
         }
+
+        Ok(())
+    }
+
+    fn reload(&mut self, world: &mut World) -> Result<(), Error> {
+        // If we didn't open from a file, our tables are the only truth, nothing to reload
+        let Some(offset) = self.open_table else {
+            return Ok(());
+        };
+
+        // Already reloading, no need to start another one
+        if self.pending_read.as_ref().map(|read| read.offset) == Some(offset) {
+            return Ok(());
+        }
+
+        event!(Level::INFO, "reloading tables");
+
+        // Any read still in flight is now stale, and will be discarded when it arrives
+        self.tables.clear();
+        let id = read_table(world, &self.file, self.sender.clone(), offset)?;
+        self.pending_read = Some(PendingRead { id, offset });
+
+        Ok(())
     }
 
     fn on_read_result(
@@ -142,29 +243,37 @@ impl Service {
     ) -> Result<(), Error> {
         event!(Level::DEBUG, "received read result");
 
+        // A reload may have started a new read, in which case this data is stale
+        let Some(pending) = self.pending_read.take_if(|read| read.id == message.id) else {
+            event!(Level::DEBUG, "discarding stale read result");
+            return Ok(());
+        };
+
         // TODO: This is where validation should happen.
 
         // TODO: Retry if the table's valid data is larger than what we've read.
         // This happens if the read length heuristic is too small, we need to retry then.
 
+        // If the file changed while reading the chain, we have to start over
+        if let Err(file::Error::Changed) = message.result {
+            event!(Level::WARN, "file changed while reading tables");
+            return self.reload(world);
+        }
+
         // Attempt to parse the table
         let data = message.result?;
-        let offset = self.pending_read.context("no pending read")?;
-        let (table, next) = Table::deserialize(offset, &data)?;
+        let (table, next) = Table::deserialize(pending.offset, &data)?;
 
         // Track the table we've at this point successfully parsed
         self.tables.push(table);
 
-        // If we have a next table, queue it up for the next read
-        if let Some(value) = next {
-            let offset = value.get();
-            read_table(world, &self.file, self.sender.clone(), offset)?;
-            self.pending_read = Some(offset);
+        // If we have a next table, queue it up for the next read, otherwise we're done reading
+        // and can start doing tasks that depend on this
+        if let Some(offset) = next.map(|value| value.get()) {
+            let id = read_table(world, &self.file, self.sender.clone(), offset)?;
+            self.pending_read = Some(PendingRead { id, offset });
         }
 
-        // We're done reading, store so we can start doing tasks that depend on this
-        self.pending_read = None;
-
         Ok(())
     }
 
@@ -173,30 +282,49 @@ impl Service {
         world: &mut World,
         message: file::WriteResponse,
     ) -> Result<(), Error> {
-        // Reply back on pending writes that we've succeeded
         let flush = self
             .pending_flush
             .remove(&message.id)
             .context("can't find pending flush")?;
-        for (id, on_result) in flush {
-            on_result.handle(world, id);
+
+        // We can't report back a failure, so we can't continue without a consistent table
+        let offset = message.result.context("failed to write table")?;
+
+        // The table can be flushed again, if it was changed in the meantime
+        if let Some(table) = self.tables.get_mut(flush.table) {
+            if flush.allocate {
+                event!(Level::DEBUG, offset, "allocated table");
+                table.complete_allocate(offset);
+            } else {
+                table.complete_flush();
+            }
+        }
+
+        // Reply back on pending writes that we've succeeded
+        for (id, on_result) in flush.on_result {
+            on_result.handle(world, (id, Ok(())));
         }
 
         Ok(())
     }
 
     fn update_tasks(&mut self, world: &mut World) {
+        let pending_read = self.pending_read.is_some();
+
         // Resolve gets we can resolve
         self.get_tasks
-            .retain(|id, action| update_get(world, &self.tables, *id, action));
+            .retain(|id, action| update_get(world, &self.tables, pending_read, *id, action));
 
         // Resolve sets we can resolve
-        self.set_tasks.retain(|id, action| {
-            update_set(&mut self.tables, self.pending_read.is_some(), *id, action)
-        });
+        self.set_tasks
+            .retain(|id, action| update_set(world, &mut self.tables, pending_read, *id, action));
+
+        // Resolve removes we can resolve
+        self.remove_tasks
+            .retain(|id, action| update_remove(world, &mut self.tables, pending_read, *id, action));
 
         // Check any marked dirty tables for write flush
-        for table in &mut self.tables {
+        for (index, table) in self.tables.iter_mut().enumerate() {
             if let Some(flush) = table.poll_flush() {
                 event!(
                     Level::DEBUG,
@@ -209,29 +337,90 @@ impl Service {
                     world,
                     &self.file,
                     table,
+                    Some(table.table_offset()),
                     self.sender.clone().map(Message::WriteResult),
                 )
                 .unwrap();
+                let flush = PendingFlush {
+                    table: index,
+                    allocate: false,
+                    on_result: flush,
+                };
                 self.pending_flush.insert(id, flush);
             }
         }
+
+        // Resolve lists, once we've read all tables and all changes have been written
+        if !pending_read && self.pending_flush.is_empty() {
+            for (id, action) in self.list_tasks.drain() {
+                let ids = self.tables.iter().flat_map(Table::ids).collect();
+                action.on_result.handle(world, (id, Ok(ids)));
+            }
+        }
+    }
+
+    /// Fail every pending action with the failure reason.
+    fn fail_tasks(&mut self, world: &mut World) {
+        let failure = self.failure.clone().unwrap_or_default();
+        let error = || source::Error::InternalError {
+            error: failure.clone(),
+        };
+
+        for (id, action) in self.get_tasks.drain() {
+            action.on_result.handle(world, (id, Err(error())));
+        }
+        for (id, action) in self.set_tasks.drain() {
+            action.on_result.handle(world, (id, Err(error())));
+        }
+        for (id, action) in self.list_tasks.drain() {
+            action.on_result.handle(world, (id, Err(error())));
+        }
+        for (id, action) in self.remove_tasks.drain() {
+            action.on_result.handle(world, (id, Err(error())));
+        }
+
+        // Changes that haven't been flushed, or whose flush is in flight, won't be anymore
+        let unflushed = self.tables.iter_mut().flat_map(Table::take_dirty).chain(
+            self.pending_flush
+                .drain()
+                .flat_map(|(_, flush)| flush.on_result),
+        );
+        for (id, on_result) in unflushed.collect::<Vec<_>>() {
+            on_result.handle(world, (id, Err(error())));
+        }
+
+        self.tables.clear();
+        self.pending_read = None;
     }
 }
 
-fn update_get(world: &mut World, tables: &[Table], id: Uuid, action: &GetAction) -> bool {
-    let (offset, size) = if let Some(value) = find_in(tables, action.id) {
-        value
-    } else {
+fn update_get(
+    world: &mut World,
+    tables: &[Table],
+    pending_read: bool,
+    id: Uuid,
+    action: &GetAction,
+) -> bool {
+    let found = find_in(tables, action.id);
+
+    // If we haven't found it yet, it may still be in a table we haven't read yet
+    if found.is_none() && pending_read {
         return true;
-    };
+    }
 
-    event!(Level::DEBUG, id = ?action.id, "found entry");
-    action.on_result.handle(world, (id, offset, size));
+    event!(Level::DEBUG, id = ?action.id, found = found.is_some(), "resolved entry");
+    action.on_result.handle(world, (id, Ok(found)));
 
     false
 }
 
-fn update_set(tables: &mut [Table], pending_read: bool, id: Uuid, action: &SetAction) -> bool {
+fn update_set(
+    world: &mut World,
+    tables: &mut [Table],
+    pending_read: bool,
+    id: Uuid,
+    action: &SetAction,
+) -> bool {
     // We can't do anything if we're not done reading in yet first
     if pending_read {
         return true;
@@ -244,13 +433,59 @@ fn update_set(tables: &mut [Table], pending_read: bool, id: Uuid, action: &SetAc
             Level::ERROR,
             "cannot insert entry, allocation not yet implemented"
         );
+        let error = source::Error::InternalError {
+            error: "no table has room for the entry".to_string(),
+        };
+        action.on_result.handle(world, (id, Err(error)));
         return false;
     }
 
     false
 }
 
+fn update_remove(
+    world: &mut World,
+    tables: &mut [Table],
+    pending_read: bool,
+    id: Uuid,
+    action: &RemoveAction,
+) -> bool {
+    // We can't do anything if we're not done reading in yet first
+    if pending_read {
+        return true;
+    }
+
+    // Remove it from the table it's in, responding once that table is flushed
+    let on_result = action
+        .on_result
+        .clone()
+        .map(|(id, result): (Uuid, Result<(), source::Error>)| (id, result.map(|_| true)));
+    let removed = tables
+        .iter_mut()
+        .any(|table| table.try_remove(action.id, id, &on_result));
+
+    event!(Level::DEBUG, id = ?action.id, removed, "resolved remove");
+    if !removed {
+        action.on_result.handle(world, (id, Ok(false)));
+    }
+
+    false
+}
+
 fn try_insert_any(tables: &mut [Table], action: &SetAction, uuid: Uuid) -> bool {
+    // If the entry already exists, update it in place
+    for table in tables.iter_mut() {
+        if table.try_replace(
+            action.id,
+            action.offset,
+            action.size,
+            uuid,
+            &action.on_result,
+        ) {
+            return true;
+        }
+    }
+
     // Find a table with an empty slot
     for table in tables {
         if table.try_insert(
@@ -276,7 +511,7 @@ fn read_table(
     file: &Handler<file::Request>,
     sender: Handler<Message>,
     offset: u64,
-) -> Result<(), Error> {
+) -> Result<Uuid, Error> {
     // Estimate the size of the table, so we can hopefully prefetch all of it
     let size = (size_of::<Header>() + (size_of::<Index>() * 256)) as u64;
 
@@ -286,19 +521,21 @@ fn read_table(
         size,
         on_result: sender.map(Message::ReadResult),
     };
+    let id = Uuid::new_v4();
     let message = file::Request {
-        id: Uuid::new_v4(),
+        id,
         action: file::Action::Read(action),
     };
     file.handle(world, message);
 
-    Ok(())
+    Ok(id)
 }
 
 fn write_table(
     world: &mut World,
     file: &Handler<file::Request>,
     table: &Table,
+    offset: Option<u64>,
     on_result: Handler<file::WriteResponse>,
 ) -> Result<Uuid, Error> {
     let id = Uuid::new_v4();
@@ -307,7 +544,7 @@ fn write_table(
 
     // Send to file for writing
     let action = file::WriteAction {
-        offset: Some(table.table_offset()),
+        offset,
         data,
         on_result,
     };
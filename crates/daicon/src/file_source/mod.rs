@@ -34,3 +34,173 @@ impl Default for FileSourceOptions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    use futures::executor::block_on;
+    use stewart::{Handler, Id as ActorId, World};
+    use uuid::Uuid;
+
+    use super::{open_file_source, FileSourceOptions};
+    use crate::{
+        open_memory_file,
+        protocol::{
+            file,
+            source::{self, Error, Id},
+        },
+        Executor, MemoryFile, SourceClient,
+    };
+
+    fn open(file: &MemoryFile, options: FileSourceOptions) -> SourceClient {
+        let executor = Executor::default();
+
+        let source = executor
+            .with_world(|world| {
+                let file = open_memory_file(world, ActorId::none(), file.clone())?;
+                open_file_source(world, ActorId::none(), file, options)
+            })
+            .unwrap();
+        executor.run_until_idle().unwrap();
+
+        SourceClient::new(executor, source)
+    }
+
+    fn list(client: &SourceClient) -> Vec<u32> {
+        let mut ids: Vec<_> = block_on(client.list())
+            .unwrap()
+            .into_iter()
+            .map(|id| id.0)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn set_get_list_remove_round_trip() {
+        let file = MemoryFile::default();
+        let client = open(&file, FileSourceOptions::default());
+
+        block_on(client.set(Id(1), b"first".to_vec())).unwrap();
+        block_on(client.set(Id(2), b"second".to_vec())).unwrap();
+
+        assert_eq!(block_on(client.get(Id(1))).unwrap(), b"first");
+        assert_eq!(block_on(client.get(Id(2))).unwrap(), b"second");
+        assert_eq!(block_on(client.stat(Id(2))).unwrap().size, 6);
+        assert_eq!(list(&client), [1, 2]);
+
+        block_on(client.remove(Id(1))).unwrap();
+        assert!(matches!(block_on(client.get(Id(1))), Err(Error::NotFound)));
+        assert!(matches!(
+            block_on(client.remove(Id(1))),
+            Err(Error::NotFound)
+        ));
+        assert_eq!(list(&client), [2]);
+
+        // Everything has been flushed, so opening the file again finds the same entries
+        let client = open(&file, FileSourceOptions::default().open_table(0));
+        assert_eq!(block_on(client.get(Id(2))).unwrap(), b"second");
+        assert!(matches!(block_on(client.get(Id(1))), Err(Error::NotFound)));
+        assert_eq!(list(&client), [2]);
+    }
+
+    #[test]
+    fn set_replaces_existing_entry() {
+        let file = MemoryFile::default();
+        let client = open(&file, FileSourceOptions::default());
+
+        block_on(client.set(Id(1), b"old".to_vec())).unwrap();
+        block_on(client.set(Id(1), b"new".to_vec())).unwrap();
+
+        assert_eq!(block_on(client.get(Id(1))).unwrap(), b"new");
+        assert_eq!(list(&client), [1]);
+    }
+
+    #[test]
+    fn set_fails_when_tables_are_full() {
+        let file = MemoryFile::default();
+        let client = open(&file, FileSourceOptions::default().allocate_capacity(1));
+
+        block_on(client.set(Id(1), b"fits".to_vec())).unwrap();
+        let result = block_on(client.set(Id(2), b"doesn't fit".to_vec()));
+
+        assert!(matches!(result, Err(Error::InternalError { .. })));
+        assert_eq!(list(&client), [1]);
+    }
+
+    #[test]
+    fn get_truncated_entry() {
+        let file = MemoryFile::default();
+        let client = open(&file, FileSourceOptions::default());
+        block_on(client.set(Id(1), b"data".to_vec())).unwrap();
+
+        // The data is appended after the table, so cutting off the end cuts off the data
+        let mut data = file.snapshot();
+        data.pop();
+        let client = open(
+            &MemoryFile::new(data),
+            FileSourceOptions::default().open_table(0),
+        );
+
+        assert!(matches!(block_on(client.get(Id(1))), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn open_empty_file_reports_reason() {
+        let client = open(
+            &MemoryFile::default(),
+            FileSourceOptions::default().open_table(0),
+        );
+
+        let Err(Error::InternalError { error }) = block_on(client.list()) else {
+            panic!("expected list to fail");
+        };
+        assert_eq!(error, "table header truncated by end of file");
+
+        // The reason is kept for later requests
+        assert!(matches!(
+            block_on(client.get(Id(1))),
+            Err(Error::InternalError { .. })
+        ));
+    }
+
+    #[test]
+    fn list_waits_for_new_table() {
+        let file = MemoryFile::default();
+        let mut world = World::default();
+
+        // Hold back file requests, so the list arrives while the new table is being written
+        let held = Rc::new(RefCell::new(Vec::new()));
+        let held_ref = held.clone();
+        let hold =
+            Handler::none().map(move |request: file::Request| held_ref.borrow_mut().push(request));
+        let options = FileSourceOptions::default().allocate_capacity(4);
+        let source = open_file_source(&mut world, ActorId::none(), hold, options).unwrap();
+
+        let answered = Rc::new(Cell::new(false));
+        let answered_ref = answered.clone();
+        let action = source::ListAction {
+            on_result: Handler::none().map(move |_: source::ListResponse| answered_ref.set(true)),
+        };
+        let request = source::Request {
+            id: Uuid::new_v4(),
+            action: source::Action::List(action),
+        };
+        source.handle(&mut world, request);
+        world.run_until_idle().unwrap();
+        assert!(!answered.get());
+
+        // Once the table is written, the list can be answered
+        let memory = open_memory_file(&mut world, ActorId::none(), file.clone()).unwrap();
+        for request in held.take() {
+            memory.handle(&mut world, request);
+        }
+        world.run_until_idle().unwrap();
+        assert!(answered.get());
+        assert_eq!(file.len(), 24 + 4 * 12);
+    }
+}
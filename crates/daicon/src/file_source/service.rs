@@ -1,13 +1,15 @@
 use std::collections::HashMap;
 
-use anyhow::{Context as _, Error};
+use anyhow::{bail, Context as _, Error};
 use daicon_types::Id as FileId;
 use stewart::{Actor, Context, Handler, Id, World};
 use tracing::{event, instrument, Level};
 use uuid::Uuid;
 
 use crate::{
-    file_source::indices::{self, Action, GetAction, SetAction},
+    file_source::indices::{
+        self, Action, GetAction, ListAction, Location, RemoveAction, SetAction,
+    },
     protocol::{file, source},
     FileSourceOptions,
 };
@@ -57,6 +59,10 @@ struct Service {
 }
 
 struct PendingGet {
+    id: FileId,
+    size: u32,
+    /// If true, the tables have already been reloaded once for this get.
+    reloaded: bool,
     on_result: Handler<source::GetResponse>,
 }
 
@@ -68,7 +74,8 @@ struct PendingSet {
 
 enum Message {
     Request(source::Request),
-    GetIndexResult((Uuid, u64, u32)),
+    GetIndexResult((Uuid, Result<Location, source::Error>)),
+    GetReadDataResult(file::ReadResponse),
     SetWriteDataResult(file::WriteResponse),
 }
 
@@ -81,8 +88,11 @@ impl Actor for Service {
                 Message::Request(message) => {
                     self.on_message(world, message)?;
                 }
-                Message::GetIndexResult((action_id, offset, size)) => {
-                    self.on_get_index_result(world, action_id, offset, size)?;
+                Message::GetIndexResult((action_id, found)) => {
+                    self.on_get_index_result(world, action_id, found)?;
+                }
+                Message::GetReadDataResult(response) => {
+                    self.on_get_read_data_result(world, response)?;
                 }
                 Message::SetWriteDataResult(result) => {
                     self.on_set_write_data_result(world, result)?;
@@ -104,7 +114,13 @@ impl Service {
                 self.on_set(world, message.id, action)?;
             }
             source::Action::List(action) => {
-                self.on_list(world, message.id, action)?;
+                self.on_list(world, message.id, action);
+            }
+            source::Action::Stat(action) => {
+                self.on_stat(world, message.id, action);
+            }
+            source::Action::Remove(action) => {
+                self.on_remove(world, message.id, action);
             }
         }
 
@@ -121,6 +137,9 @@ impl Service {
 
         // Track the get task
         let task = PendingGet {
+            id: action.id,
+            size: 0,
+            reloaded: false,
             on_result: action.on_result,
         };
         self.get_tasks.insert(id, task);
@@ -158,41 +177,148 @@ impl Service {
         Ok(())
     }
 
-    fn on_list(
+    fn on_list(&mut self, world: &mut World, id: Uuid, action: source::ListAction) {
+        event!(Level::INFO, "received list");
+
+        // The indices have all the information we need, so reply directly from there
+        let on_result = action
+            .on_result
+            .map(|(id, result)| source::ListResponse { id, result });
+        let message = indices::Request {
+            id,
+            action: Action::List(ListAction { on_result }),
+        };
+        self.indices.handle(world, message);
+    }
+
+    fn on_stat(&mut self, world: &mut World, id: Uuid, action: source::StatAction) {
+        event!(Level::INFO, id = ?action.id, "received stat");
+
+        // The indices have all the information we need, so reply directly from there
+        let on_result =
+            action
+                .on_result
+                .map(|(id, result): (Uuid, Result<Location, source::Error>)| {
+                    let result = result.and_then(|found| {
+                        found
+                            .map(|(_, size)| source::Stat { size: size as u64 })
+                            .ok_or(source::Error::NotFound)
+                    });
+                    source::StatResponse { id, result }
+                });
+        let action = GetAction {
+            id: action.id,
+            on_result,
+        };
+        let message = indices::Request {
+            id,
+            action: Action::Get(action),
+        };
+        self.indices.handle(world, message);
+    }
+
+    fn on_remove(&mut self, world: &mut World, id: Uuid, action: source::RemoveAction) {
+        event!(Level::INFO, id = ?action.id, "received remove");
+
+        // Removing only touches the tables, so reply directly from the indices
+        let on_result = action
+            .on_result
+            .map(|(id, result): (Uuid, Result<bool, _>)| {
+                let result = result.and_then(|removed| {
+                    if removed {
+                        Ok(())
+                    } else {
+                        Err(source::Error::NotFound)
+                    }
+                });
+                source::RemoveResponse { id, result }
+            });
+        let action = RemoveAction {
+            id: action.id,
+            on_result,
+        };
+        let message = indices::Request {
+            id,
+            action: Action::Remove(action),
+        };
+        self.indices.handle(world, message);
+    }
+
+    fn on_get_index_result(
         &mut self,
         world: &mut World,
         id: Uuid,
-        action: source::ListAction,
+        result: Result<Location, source::Error>,
     ) -> Result<(), Error> {
-        // TODO: Actually do this
-        let error = source::Error::InternalError {
-            error: "not yet implemented".to_string(),
+        event!(Level::DEBUG, ?id, "received get index result");
+
+        // If there's no entry, or the indices failed, we're already done
+        let Ok(Some((offset, size))) = result else {
+            let task = self
+                .get_tasks
+                .remove(&id)
+                .context("failed to find get task")?;
+            let response = source::GetResponse {
+                id,
+                result: result.and(Err(source::Error::NotFound)),
+            };
+            task.on_result.handle(world, response);
+
+            return Ok(());
         };
-        let response = source::ListResponse {
-            id,
-            result: Err(error),
+
+        let Some(task) = self.get_tasks.get_mut(&id) else {
+            bail!("failed to find get task");
         };
-        action.on_result.handle(world, response);
+        task.size = size;
+
+        // We've got the location of the data, so perform the read
+        self.send_read_data(world, id, offset, size);
+
         Ok(())
     }
 
-    fn on_get_index_result(
+    fn on_get_read_data_result(
         &mut self,
         world: &mut World,
-        id: Uuid,
-        offset: u64,
-        size: u32,
+        response: file::ReadResponse,
     ) -> Result<(), Error> {
-        event!(Level::DEBUG, ?id, "received get index result");
+        event!(Level::DEBUG, id = ?response.id, "received get read data result");
 
         // Remove the task, we're done with it in this actor
-        let task = self
+        let mut task = self
             .get_tasks
-            .remove(&id)
+            .remove(&response.id)
             .context("failed to find get task")?;
 
-        // We've got the location of the data, so perform the read
-        self.send_read_data(world, id, offset, size, task.on_result);
+        // If the file changed since we read the tables, the location we read may be stale
+        if let (Err(file::Error::Changed), false) = (&response.result, task.reloaded) {
+            event!(Level::WARN, id = ?task.id, "file changed, reloading tables");
+
+            self.send_reload(world, response.id);
+
+            // Try again with the reloaded tables
+            let id = task.id;
+            task.reloaded = true;
+            self.get_tasks.insert(response.id, task);
+            self.send_read_index(world, response.id, id);
+
+            return Ok(());
+        }
+
+        // Reads may be short, which means the file doesn't contain all of the entry's data
+        let result = match response.result {
+            Ok(data) if data.len() < task.size as usize => Err(source::Error::Truncated),
+            Ok(data) => Ok(data),
+            Err(error) => Err(source::Error::InternalError {
+                error: error.to_string(),
+            }),
+        };
+        let response = source::GetResponse {
+            id: response.id,
+            result,
+        };
+        task.on_result.handle(world, response);
 
         Ok(())
     }
@@ -210,8 +336,23 @@ impl Service {
             .remove(&response.id)
             .context("failed to get pending set task")?;
 
+        // If writing the data failed, there's no index to write
+        let offset = match response.result {
+            Ok(offset) => offset,
+            Err(error) => {
+                let response = source::SetResponse {
+                    id: response.id,
+                    result: Err(source::Error::InternalError {
+                        error: error.to_string(),
+                    }),
+                };
+                task.on_result.handle(world, response);
+
+                return Ok(());
+            }
+        };
+
         // Write the index
-        let offset = response.result?;
         self.send_write_index(world, response.id, task, offset);
 
         Ok(())
@@ -227,6 +368,14 @@ impl Service {
         self.indices.handle(world, message);
     }
 
+    fn send_reload(&self, world: &mut World, id: Uuid) {
+        let message = indices::Request {
+            id,
+            action: Action::Reload,
+        };
+        self.indices.handle(world, message);
+    }
+
     fn send_write_index(&self, world: &mut World, id: Uuid, task: PendingSet, offset: u64) {
         let action = SetAction {
             id: task.id,
@@ -234,7 +383,7 @@ impl Service {
             size: task.size,
             on_result: task
                 .on_result
-                .map(move |_| source::SetResponse { id, result: Ok(()) }),
+                .map(|(id, result)| source::SetResponse { id, result }),
         };
         let message = indices::Request {
             id,
@@ -243,23 +392,11 @@ impl Service {
         self.indices.handle(world, message);
     }
 
-    fn send_read_data(
-        &self,
-        world: &mut World,
-        id: Uuid,
-        offset: u64,
-        size: u32,
-        on_result: Handler<source::GetResponse>,
-    ) {
+    fn send_read_data(&self, world: &mut World, id: Uuid, offset: u64, size: u32) {
         let action = file::ReadAction {
             offset,
             size: size as u64,
-            on_result: on_result.map(|f: file::ReadResponse| source::GetResponse {
-                id: f.id,
-                result: f.result.map_err(|e| source::Error::InternalError {
-                    error: e.to_string(),
-                }),
-            }),
+            on_result: self.handler.clone().map(Message::GetReadDataResult),
         };
         let message = file::Request {
             id,
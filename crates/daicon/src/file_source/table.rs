@@ -3,16 +3,22 @@ use std::{
     num::NonZeroU64,
 };
 
-use anyhow::Error;
+use anyhow::{bail, Context as _, Error};
 use bytemuck::{bytes_of, bytes_of_mut, cast_slice_mut};
 use daicon_types::{Header, Id, Index};
 use stewart::Handler;
 use uuid::Uuid;
 
+use crate::protocol::source;
+
+/// Handler reporting back if a change to a table was flushed to the file.
+pub type OnFlush = Handler<(Uuid, Result<(), source::Error>)>;
+
 /// Cached in-memory file table.
 pub struct Table {
     table_offset: u64,
-    dirty: Option<Vec<(Uuid, Handler<Uuid>)>>,
+    dirty: Option<Vec<(Uuid, OnFlush)>>,
+    flushing: bool,
 
     entries_offset: u64,
     capacity: u16,
@@ -20,10 +26,14 @@ pub struct Table {
 }
 
 impl Table {
+    /// Create a new table, that still has to be allocated in the file.
+    ///
+    /// Until `complete_allocate` is called, the table counts as being flushed.
     pub fn new(capacity: u16) -> Self {
         Self {
             table_offset: 0,
             dirty: None,
+            flushing: true,
 
             entries_offset: 0,
             capacity,
@@ -38,8 +48,38 @@ impl Table {
 
     /// Check if we need to flush, and if so return `Some` with handlers that need to be called on
     /// successful flush.
-    pub fn poll_flush(&mut self) -> Option<Vec<(Uuid, Handler<Uuid>)>> {
-        self.dirty.take()
+    ///
+    /// While a previous flush hasn't completed yet, this returns `None`, as writes may complete
+    /// out of order and an older flush could overwrite a newer one.
+    pub fn poll_flush(&mut self) -> Option<Vec<(Uuid, OnFlush)>> {
+        if self.flushing {
+            return None;
+        }
+
+        let dirty = self.dirty.take();
+        self.flushing = dirty.is_some();
+        dirty
+    }
+
+    /// Take the handlers of changes that haven't been flushed yet, without flushing them.
+    pub fn take_dirty(&mut self) -> Vec<(Uuid, OnFlush)> {
+        self.dirty.take().unwrap_or_default()
+    }
+
+    /// Mark the previous flush as completed.
+    pub fn complete_flush(&mut self) {
+        self.flushing = false;
+    }
+
+    /// Mark the write allocating the table as completed, at the offset it was allocated at.
+    pub fn complete_allocate(&mut self, table_offset: u64) {
+        self.table_offset = table_offset;
+        self.flushing = false;
+    }
+
+    /// Get the IDs of all valid entries.
+    pub fn ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.entries.iter().map(Index::id)
     }
 
     pub fn find(&self, id: Id) -> Option<(u64, u32)> {
@@ -59,7 +99,7 @@ impl Table {
         offset: u64,
         size: u32,
         uuid: Uuid,
-        on_result: &Handler<Uuid>,
+        on_result: &OnFlush,
     ) -> bool {
         // Check if we have any room at all
         if self.entries.len() >= self.capacity as usize {
@@ -67,27 +107,75 @@ impl Table {
         }
 
         // Check if the offset is in-range
-        if offset < self.entries_offset || (offset - self.entries_offset) > u32::MAX as u64 {
+        let Some(relative) = self.relative_offset(offset) else {
             return false;
-        }
+        };
 
         // We can now insert it
-        let relative = offset - self.entries_offset;
-
         let mut entry = Index::default();
         entry.set_id(id);
-        entry.set_offset(relative as u32);
+        entry.set_offset(relative);
         entry.set_size(size);
 
         self.entries.push(entry);
 
-        // Mark dirty since we've now got data to write back
-        let dirty = self.dirty.get_or_insert_with(Vec::new);
-        dirty.push((uuid, on_result.clone()));
+        self.mark_dirty(uuid, on_result);
+
+        true
+    }
+
+    /// Try replacing the location of an existing entry, with a handler to report back when flush
+    /// succeeds.
+    pub fn try_replace(
+        &mut self,
+        id: Id,
+        offset: u64,
+        size: u32,
+        uuid: Uuid,
+        on_result: &OnFlush,
+    ) -> bool {
+        let Some(relative) = self.relative_offset(offset) else {
+            return false;
+        };
+
+        let Some(entry) = self.entries.iter_mut().find(|entry| entry.id() == id) else {
+            return false;
+        };
+
+        entry.set_offset(relative);
+        entry.set_size(size);
+
+        self.mark_dirty(uuid, on_result);
 
         true
     }
 
+    /// Try removing an entry, with a handler to report back when flush succeeds.
+    ///
+    /// This only removes the entry from the table, the data it points to is left in the file.
+    pub fn try_remove(&mut self, id: Id, uuid: Uuid, on_result: &OnFlush) -> bool {
+        let Some(index) = self.entries.iter().position(|entry| entry.id() == id) else {
+            return false;
+        };
+
+        self.entries.remove(index);
+
+        self.mark_dirty(uuid, on_result);
+
+        true
+    }
+
+    fn relative_offset(&self, offset: u64) -> Option<u32> {
+        let relative = offset.checked_sub(self.entries_offset)?;
+        u32::try_from(relative).ok()
+    }
+
+    /// Mark dirty since we've now got data to write back.
+    fn mark_dirty(&mut self, uuid: Uuid, on_result: &OnFlush) {
+        let dirty = self.dirty.get_or_insert_with(Vec::new);
+        dirty.push((uuid, on_result.clone()));
+    }
+
     pub fn serialize(&self) -> Result<Vec<u8>, Error> {
         let mut data = Vec::new();
 
@@ -118,17 +206,29 @@ impl Table {
     ) -> Result<(Self, Option<NonZeroU64>), Error> {
         let mut data = Cursor::new(data);
 
-        // Read the header
+        // Read the header, reads may be short so there may not be a complete table
         let mut header = Header::default();
-        data.read_exact(bytes_of_mut(&mut header))?;
+        data.read_exact(bytes_of_mut(&mut header))
+            .context("table header truncated by end of file")?;
+
+        if !header.is_valid() {
+            bail!("invalid table signature");
+        }
+
+        // Tables are written with padding up to capacity, so this can't be trusted
+        if header.valid() > header.capacity() {
+            bail!("table has more valid entries than its capacity");
+        }
 
         // Read entries
         let mut entries = vec![Index::default(); header.valid() as usize];
-        data.read_exact(cast_slice_mut(&mut entries))?;
+        data.read_exact(cast_slice_mut(&mut entries))
+            .context("table entries truncated by end of file")?;
 
         let table = Self {
             table_offset,
             dirty: None,
+            flushing: false,
 
             entries_offset: header.offset(),
             capacity: header.capacity(),
@@ -137,3 +237,52 @@ impl Table {
         Ok((table, header.next()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::bytes_of;
+    use daicon_types::{Header, Id, Index};
+
+    use super::Table;
+
+    fn table_data(capacity: u16, valid: u16) -> Vec<u8> {
+        let mut header = Header::default();
+        header.set_offset(64);
+        header.set_capacity(capacity);
+        header.set_valid(valid);
+
+        let mut index = Index::default();
+        index.set_id(Id(1));
+        index.set_size(8);
+
+        // Used slots, followed by empty padding up to capacity
+        let mut data = bytes_of(&header).to_vec();
+        for _ in 0..valid {
+            data.extend_from_slice(bytes_of(&index));
+        }
+        for _ in valid..capacity {
+            data.extend_from_slice(bytes_of(&Index::default()));
+        }
+        data
+    }
+
+    #[test]
+    fn deserialize_serialize_round_trip() {
+        let (table, next) = Table::deserialize(0, &table_data(2, 1)).unwrap();
+
+        assert_eq!(table.find(Id(1)), Some((64, 8)));
+        assert_eq!(next, None);
+        assert_eq!(table.serialize().unwrap(), table_data(2, 1));
+    }
+
+    #[test]
+    fn deserialize_rejects_valid_exceeding_capacity() {
+        let result = Table::deserialize(0, &table_data(2, 3));
+
+        let error = result.err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "table has more valid entries than its capacity"
+        );
+    }
+}
@@ -5,8 +5,32 @@
 //! Daicon lookup is abstracted as a "source", which lets you look up data by ID.
 //! Higher level abstractions, such as error checking, can be implemented by implementing the
 //! source protocol on top of another source.
+//!
+//! Sources read and write their data through the "file" protocol, platform crates implement this
+//! for system files and the web.
+//! `open_memory_file` implements it in memory, to build and read packages without touching any
+//! platform.
+//!
+//! # Executors
+//!
+//! Platform implementations complete their work outside of the world, such as from a browser
+//! event loop or another thread.
+//! `Executor` owns the world, and queues these completions so they can be handed to it safely.
+//!
+//! # Async/Await
+//!
+//! If you don't want to write an actor to talk to a source, `SourceClient` wraps a source in
+//! methods returning futures.
 
+mod executor;
 mod file_source;
+mod memory_file;
 pub mod protocol;
+mod source_client;
 
-pub use self::file_source::{open_file_source, FileSourceOptions};
+pub use self::{
+    executor::{Executor, RemoteSender},
+    file_source::{open_file_source, FileSourceOptions},
+    memory_file::{open_memory_file, MemoryFile},
+    source_client::SourceClient,
+};
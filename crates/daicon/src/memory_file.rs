@@ -0,0 +1,226 @@
+use std::{cell::RefCell, mem, rc::Rc};
+
+use anyhow::Error;
+use stewart::{Actor, Context, Handler, Id, World};
+use tracing::{event, instrument, Level};
+
+use crate::protocol::file;
+
+/// In-memory file data, shared with the actor started by `open_memory_file`.
+///
+/// Cloning is cheap, all clones share the same data.
+#[derive(Clone, Default)]
+pub struct MemoryFile {
+    data: Rc<RefCell<Vec<u8>>>,
+}
+
+impl MemoryFile {
+    /// Create a file with the given initial data.
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data: Rc::new(RefCell::new(data)),
+        }
+    }
+
+    /// Get the size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.data.borrow().len() as u64
+    }
+
+    /// Returns true if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.data.borrow().is_empty()
+    }
+
+    /// Get a copy of the current data.
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.data.borrow().clone()
+    }
+
+    /// Take out the current data, without copying, leaving the file empty.
+    pub fn export(&self) -> Vec<u8> {
+        mem::take(&mut self.data.borrow_mut())
+    }
+}
+
+/// Open an in-memory file.
+///
+/// Writes without an offset append to the end of the file.
+#[instrument(skip_all)]
+pub fn open_memory_file(
+    world: &mut World,
+    id: Id,
+    file: MemoryFile,
+) -> Result<Handler<file::Request>, Error> {
+    event!(Level::INFO, "opening");
+
+    let id = world.create(id, "daicon-memory-file")?;
+
+    let actor = MemoryFileService { file };
+    world.start(id, actor)?;
+
+    Ok(Handler::to(id))
+}
+
+struct MemoryFileService {
+    file: MemoryFile,
+}
+
+impl Actor for MemoryFileService {
+    type Message = file::Request;
+
+    fn process(&mut self, world: &mut World, mut cx: Context<Self>) -> Result<(), Error> {
+        while let Some(message) = cx.next() {
+            match message.action {
+                file::Action::Read(action) => {
+                    event!(Level::DEBUG, "reading from memory file");
+
+                    let result = file::ReadResponse {
+                        id: message.id,
+                        result: Ok(self.read(action.offset, action.size)),
+                    };
+                    action.on_result.handle(world, result);
+                }
+                file::Action::Write(action) => {
+                    event!(Level::DEBUG, "writing to memory file");
+
+                    let result = file::WriteResponse {
+                        id: message.id,
+                        result: self.write(action.offset, &action.data),
+                    };
+                    action.on_result.handle(world, result);
+                }
+                file::Action::Cancel(_) => {
+                    // Actions are completed immediately, so there's never anything to cancel
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MemoryFileService {
+    fn read(&self, offset: u64, size: u64) -> Vec<u8> {
+        let data = self.file.data.borrow();
+
+        // Reads past EOF are short
+        let start = (offset.min(data.len() as u64)) as usize;
+        let end = (offset.saturating_add(size).min(data.len() as u64)) as usize;
+
+        data[start..end].to_vec()
+    }
+
+    fn write(&self, offset: Option<u64>, bytes: &[u8]) -> Result<u64, file::Error> {
+        let mut data = self.file.data.borrow_mut();
+
+        let Some(offset) = offset else {
+            let offset = data.len() as u64;
+            data.extend_from_slice(bytes);
+            return Ok(offset);
+        };
+
+        // Writes to an explicit offset have to be within the file already
+        let end = offset
+            .checked_add(bytes.len() as u64)
+            .filter(|end| *end <= data.len() as u64)
+            .ok_or(file::Error::InvalidRegion)?;
+
+        data[offset as usize..end as usize].copy_from_slice(bytes);
+
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use stewart::{Handler, Id, World};
+    use uuid::Uuid;
+
+    use super::{open_memory_file, MemoryFile};
+    use crate::protocol::file;
+
+    fn read(file: &MemoryFile, offset: u64, size: u64) -> Vec<u8> {
+        let mut world = World::default();
+        let handler = open_memory_file(&mut world, Id::none(), file.clone()).unwrap();
+
+        let result = Rc::new(RefCell::new(None));
+        let result_ref = result.clone();
+        let action = file::ReadAction {
+            offset,
+            size,
+            on_result: Handler::none()
+                .map(move |response: file::ReadResponse| *result_ref.borrow_mut() = Some(response)),
+        };
+        let request = file::Request {
+            id: Uuid::new_v4(),
+            action: file::Action::Read(action),
+        };
+        handler.handle(&mut world, request);
+        world.run_until_idle().unwrap();
+
+        let response = result.borrow_mut().take().unwrap();
+        response.result.unwrap()
+    }
+
+    fn write(file: &MemoryFile, offset: Option<u64>, data: &[u8]) -> Result<u64, file::Error> {
+        let mut world = World::default();
+        let handler = open_memory_file(&mut world, Id::none(), file.clone()).unwrap();
+
+        let result = Rc::new(RefCell::new(None));
+        let result_ref = result.clone();
+        let action = file::WriteAction {
+            offset,
+            data: data.to_vec(),
+            on_result: Handler::none().map(move |response: file::WriteResponse| {
+                *result_ref.borrow_mut() = Some(response)
+            }),
+        };
+        let request = file::Request {
+            id: Uuid::new_v4(),
+            action: file::Action::Write(action),
+        };
+        handler.handle(&mut world, request);
+        world.run_until_idle().unwrap();
+
+        let response = result.borrow_mut().take().unwrap();
+        response.result
+    }
+
+    #[test]
+    fn read_past_end_is_short() {
+        let file = MemoryFile::new(vec![1, 2, 3, 4]);
+
+        assert_eq!(read(&file, 0, 4), [1, 2, 3, 4]);
+        assert_eq!(read(&file, 2, 8), [3, 4]);
+        assert_eq!(read(&file, 8, 8), []);
+        assert_eq!(read(&file, u64::MAX, u64::MAX), []);
+    }
+
+    #[test]
+    fn write_without_offset_appends() {
+        let file = MemoryFile::new(vec![1, 2]);
+
+        assert_eq!(write(&file, None, &[3, 4]).unwrap(), 2);
+        assert_eq!(file.snapshot(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn write_region_must_be_within_file() {
+        let file = MemoryFile::new(vec![0; 4]);
+
+        assert_eq!(write(&file, Some(1), &[1, 2, 3]).unwrap(), 1);
+        assert_eq!(file.snapshot(), [0, 1, 2, 3]);
+
+        let result = write(&file, Some(2), &[1, 2, 3]);
+        assert!(matches!(result, Err(file::Error::InvalidRegion)));
+
+        let result = write(&file, Some(u64::MAX), &[1]);
+        assert!(matches!(result, Err(file::Error::InvalidRegion)));
+
+        // Failed writes don't touch the data
+        assert_eq!(file.snapshot(), [0, 1, 2, 3]);
+    }
+}
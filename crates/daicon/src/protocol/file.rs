@@ -1,3 +1,5 @@
+use std::io;
+
 use stewart::Handler;
 use thiserror::Error;
 use uuid::Uuid;
@@ -19,9 +21,15 @@ pub enum Action {
     Read(ReadAction),
     /// Write a section of data.
     Write(WriteAction),
+    /// Cancel a pending action.
+    Cancel(CancelAction),
 }
 
 /// Read a section of data.
+///
+/// Reads may be short: if the section extends past the end of the file, only the data up to the
+/// end of the file is returned, and reading entirely past the end returns no data.
+/// Callers that need the entire section have to check the length of the data.
 pub struct ReadAction {
     pub offset: u64,
     pub size: u64,
@@ -32,7 +40,8 @@ pub struct ReadAction {
 pub struct ReadResponse {
     /// Identifier of originating message.
     pub id: Uuid,
-    /// Result of the read action, containing the data read.
+    /// Result of the read action, containing the data read, which may be short at the end of the
+    /// file.
     pub result: Result<Vec<u8>, Error>,
 }
 
@@ -57,12 +66,42 @@ pub struct WriteResponse {
     pub result: Result<u64, Error>,
 }
 
+/// Cancel a pending action, by the `id` of the request that started it.
+///
+/// If the action is still pending, it will respond with `Error::Cancelled`.
+/// Files that complete actions immediately may ignore this.
+pub struct CancelAction {
+    pub id: Uuid,
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("action not supported on file")]
     NotSupported,
     #[error("write allocation failed on file")]
     WriteAllocationFailed,
+    /// The region to write to is not a valid region of the file.
+    #[error("invalid write region")]
+    InvalidRegion,
+    #[error("action was cancelled")]
+    Cancelled,
+    #[error("action timed out")]
+    TimedOut,
+    /// The file changed since it was last read, previously read data may be stale.
+    #[error("file changed")]
+    Changed,
+    /// The underlying system file failed an I/O operation.
+    #[error("i/o error ({kind})")]
+    Io { kind: io::ErrorKind, error: String },
     #[error("internal error")]
     InternalError { error: String },
 }
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io {
+            kind: error.kind(),
+            error: error.to_string(),
+        }
+    }
+}
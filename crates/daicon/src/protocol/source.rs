@@ -23,6 +23,10 @@ pub enum Action {
     Set(SetAction),
     /// Get a list of all indices in the source.
     List(ListAction),
+    /// Get metadata of the data associated with an ID, without getting the data itself.
+    Stat(StatAction),
+    /// Remove the data associated with an ID.
+    Remove(RemoveAction),
 }
 
 /// Get the data associated with an ID.
@@ -37,6 +41,9 @@ pub struct GetResponse {
 }
 
 /// Set the data associated with an ID.
+///
+/// If the ID already has an entry, that entry is updated in place to point to the new data,
+/// rather than a second entry with the same ID being added. The old data is left in the file.
 pub struct SetAction {
     pub id: Id,
     pub data: Vec<u8>,
@@ -49,17 +56,54 @@ pub struct SetResponse {
 }
 
 /// Get a list of all indices in the source.
+///
+/// Sources with pending changes answer only once those changes have been written.
 pub struct ListAction {
     pub on_result: Handler<ListResponse>,
 }
 
 pub struct ListResponse {
+    pub id: Uuid,
+    pub result: Result<Vec<Id>, Error>,
+}
+
+/// Get metadata of the data associated with an ID, without getting the data itself.
+pub struct StatAction {
+    pub id: Id,
+    pub on_result: Handler<StatResponse>,
+}
+
+pub struct StatResponse {
+    pub id: Uuid,
+    pub result: Result<Stat, Error>,
+}
+
+/// Metadata of the data associated with an ID.
+pub struct Stat {
+    /// Size of the data in bytes.
+    pub size: u64,
+}
+
+/// Remove the data associated with an ID.
+///
+/// Removing an ID that has no data associated with it results in `Error::NotFound`.
+pub struct RemoveAction {
+    pub id: Id,
+    pub on_result: Handler<RemoveResponse>,
+}
+
+pub struct RemoveResponse {
     pub id: Uuid,
     pub result: Result<(), Error>,
 }
 
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("internal error")]
+    #[error("no data associated with id")]
+    NotFound,
+    /// The data associated with the ID extends past the end of the underlying file.
+    #[error("data truncated by end of file")]
+    Truncated,
+    #[error("internal error: {error}")]
     InternalError { error: String },
 }
@@ -0,0 +1,119 @@
+use std::{cell::RefCell, future::Future};
+
+use futures::channel::oneshot;
+use stewart::Handler;
+use uuid::Uuid;
+
+use crate::{
+    protocol::source::{self, Error, Id, Stat},
+    Executor,
+};
+
+/// Async/await facade over a source.
+///
+/// Requests are sent to the source immediately when calling a method, the returned futures only
+/// wait for the response.
+/// The futures don't depend on any specific async runtime, they complete when the world handles
+/// the response, so any runtime that drives the executor's world can poll them.
+#[derive(Clone)]
+pub struct SourceClient {
+    executor: Executor,
+    source: Handler<source::Request>,
+}
+
+impl SourceClient {
+    pub fn new(executor: Executor, source: Handler<source::Request>) -> Self {
+        Self { executor, source }
+    }
+
+    /// Get the data associated with an ID.
+    pub fn get(&self, id: Id) -> impl Future<Output = Result<Vec<u8>, Error>> + 'static {
+        let receiver =
+            self.request(|on_result| source::Action::Get(source::GetAction { id, on_result }));
+
+        async move {
+            let response: source::GetResponse = receive(receiver).await?;
+            response.result
+        }
+    }
+
+    /// Set the data associated with an ID.
+    pub fn set(&self, id: Id, data: Vec<u8>) -> impl Future<Output = Result<(), Error>> + 'static {
+        let receiver = self.request(|on_result| {
+            source::Action::Set(source::SetAction {
+                id,
+                data,
+                on_result,
+            })
+        });
+
+        async move {
+            let response: source::SetResponse = receive(receiver).await?;
+            response.result
+        }
+    }
+
+    /// Get a list of all IDs in the source.
+    pub fn list(&self) -> impl Future<Output = Result<Vec<Id>, Error>> + 'static {
+        let receiver =
+            self.request(|on_result| source::Action::List(source::ListAction { on_result }));
+
+        async move {
+            let response: source::ListResponse = receive(receiver).await?;
+            response.result
+        }
+    }
+
+    /// Get metadata of the data associated with an ID, without getting the data itself.
+    pub fn stat(&self, id: Id) -> impl Future<Output = Result<Stat, Error>> + 'static {
+        let receiver =
+            self.request(|on_result| source::Action::Stat(source::StatAction { id, on_result }));
+
+        async move {
+            let response: source::StatResponse = receive(receiver).await?;
+            response.result
+        }
+    }
+
+    /// Remove the data associated with an ID.
+    pub fn remove(&self, id: Id) -> impl Future<Output = Result<(), Error>> + 'static {
+        let receiver = self
+            .request(|on_result| source::Action::Remove(source::RemoveAction { id, on_result }));
+
+        async move {
+            let response: source::RemoveResponse = receive(receiver).await?;
+            response.result
+        }
+    }
+
+    /// Send a request to the source, and receive the response through a future.
+    fn request<M, F>(&self, action: F) -> oneshot::Receiver<M>
+    where
+        M: 'static,
+        F: FnOnce(Handler<M>) -> source::Action,
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        // Complete the future when the response is handled
+        let sender = RefCell::new(Some(sender));
+        let on_result = Handler::none().map(move |response: M| {
+            if let Some(sender) = sender.borrow_mut().take() {
+                let _ = sender.send(response);
+            }
+        });
+
+        let message = source::Request {
+            id: Uuid::new_v4(),
+            action: action(on_result),
+        };
+        self.executor.send(self.source.clone(), message);
+
+        receiver
+    }
+}
+
+async fn receive<M>(receiver: oneshot::Receiver<M>) -> Result<M, Error> {
+    receiver.await.map_err(|_| Error::InternalError {
+        error: "source stopped without responding".to_string(),
+    })
+}
@@ -0,0 +1,8 @@
+#!/bin/bash
+set -e
+
+cargo build --release -p daicon-web --features bindings --target wasm32-unknown-unknown
+wasm-bindgen --out-name daicon \
+  --out-dir pkg/ \
+  --typescript \
+  --target web target/wasm32-unknown-unknown/release/daicon_web.wasm